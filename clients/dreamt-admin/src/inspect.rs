@@ -0,0 +1,246 @@
+//! Transaction inspection
+//! Describe what a transaction will do so signers can review it before signing or submission
+
+use std::fmt::Write;
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::Result;
+use dreamt_client::dreamt::instruction as ix;
+use dreamt_client::{dreamt, pda, quote};
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
+
+use crate::ops::pause_flag_names;
+use crate::transaction::{signature_statuses, SignatureStatus};
+
+/// Human readable report of a transaction's intent and signature state
+pub fn describe(transaction: &Transaction) -> Result<String> {
+    let message = &transaction.message;
+    let mut report = String::new();
+
+    writeln!(report, "Fee payer: {}", message.account_keys[0])?;
+    writeln!(report, "Blockhash: {}", message.recent_blockhash)?;
+
+    for (index, instruction) in message.instructions.iter().enumerate() {
+        let program_id = message.account_keys[instruction.program_id_index as usize];
+        writeln!(report, "Instruction {index}: {}", describe_instruction(&program_id, instruction))?;
+        for account_index in &instruction.accounts {
+            let account_index = *account_index as usize;
+            let key = message.account_keys[account_index];
+            let mut flags = Vec::new();
+            if message.is_signer(account_index) {
+                flags.push("signer");
+            }
+            if message.is_maybe_writable(account_index, None) {
+                flags.push("writable");
+            }
+            let label = account_label(&key).map(|label| format!(" ({label})")).unwrap_or_default();
+            writeln!(report, "  {key}{label} [{}]", flags.join(", "))?;
+        }
+    }
+
+    writeln!(report, "Signatures:")?;
+    let statuses = signature_statuses(transaction);
+    for (signer, status) in &statuses {
+        let status = match status {
+            SignatureStatus::Missing => "missing",
+            SignatureStatus::Valid => "signed",
+            SignatureStatus::Invalid => "INVALID",
+        };
+        writeln!(report, "  {signer}: {status}")?;
+    }
+    let complete = statuses
+        .iter()
+        .all(|(_, status)| *status == SignatureStatus::Valid);
+    writeln!(report, "Ready to submit: {}", if complete { "yes" } else { "no" })?;
+
+    Ok(report)
+}
+
+/// Helper function to decode the arguments of a DREAMT admin instruction
+fn describe_instruction(program_id: &Pubkey, instruction: &CompiledInstruction) -> String {
+    if *program_id != dreamt::ID {
+        return format!("program {program_id} ({} bytes of data)", instruction.data.len());
+    }
+
+    let data = instruction.data.as_slice();
+    if let Some(args) = decode::<ix::Pause>(data) {
+        return format!(
+            "pause [{}] (flags {:#b}, reason code {})",
+            pause_flag_names(args.flags).join(", "),
+            args.flags,
+            args.reason_code
+        );
+    }
+    if let Some(args) = decode::<ix::AddToBlacklist>(data) {
+        return format!("add_to_blacklist {}", args.address);
+    }
+    if let Some(args) = decode::<ix::UpdateMaxSupply>(data) {
+        return format!("update_max_supply to {}", args.new_max_supply);
+    }
+    if let Some(args) = decode::<ix::AdminBurn>(data) {
+        return match quote::calculate_refund_amount(args.amount) {
+            Ok(refund) => format!(
+                "admin_burn {} tokens (refund {} USDC)",
+                args.amount,
+                format_usdc(refund)
+            ),
+            Err(_) => format!("admin_burn {} tokens (refund overflows)", args.amount),
+        };
+    }
+    "unrecognized DREAMT instruction".to_string()
+}
+
+/// Helper function to decode instruction arguments behind a matching discriminator
+fn decode<T: Discriminator + AnchorDeserialize>(data: &[u8]) -> Option<T> {
+    let mut args = data.strip_prefix(T::DISCRIMINATOR)?;
+    T::deserialize(&mut args).ok()
+}
+
+/// Helper function to name the program accounts
+fn account_label(key: &Pubkey) -> Option<&'static str> {
+    [
+        (pda::token_state().0, "token_state"),
+        (pda::blacklist().0, "blacklist"),
+        (pda::guardian_set().0, "guardian_set"),
+        (pda::vault_owner().0, "vault_owner"),
+        (pda::reserve_config().0, "reserve_config"),
+        (pda::treasury().0, "treasury"),
+        (pda::event_authority().0, "event_authority"),
+        (dreamt::ID, "dreamt program"),
+    ]
+    .into_iter()
+    .find(|(address, _)| address == key)
+    .map(|(_, label)| label)
+}
+
+/// Helper function to format a raw USDC amount (6 decimals)
+fn format_usdc(amount: u64) -> String {
+    format!("{}.{:06}", amount / 1_000_000, amount % 1_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::{AdminKeys, AdminOp, BurnAccounts};
+    use crate::transaction::{build, sign};
+    use solana_sdk::hash::Hash;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    /// Helper function to describe an unsigned transaction for an admin operation
+    fn describe_op(op: AdminOp, keys: &AdminKeys) -> String {
+        let transaction = build(&[op.instruction(keys)], &keys.authority, Hash::new_unique());
+        describe(&transaction).unwrap()
+    }
+
+    fn keys() -> AdminKeys {
+        AdminKeys {
+            authority: Pubkey::new_unique(),
+            multisig: Pubkey::new_unique(),
+            cosigners: vec![Pubkey::new_unique()],
+        }
+    }
+
+    #[test]
+    fn describes_multisig_pause_with_cosigners() {
+        let keys = keys();
+        let report = describe_op(
+            AdminOp::Pause {
+                flags: 0b101,
+                reason_code: 0,
+                guardian: false,
+            },
+            &keys,
+        );
+        assert!(report.contains("pause [mint, burn] (flags 0b101, reason code 0)"));
+        assert!(report.contains(&format!("{} (token_state) [writable]", pda::token_state().0)));
+        assert!(report.contains(&format!("{} [signer]", keys.cosigners[0])));
+        assert!(report.contains(&format!("  {}: missing", keys.cosigners[0])));
+        assert!(report.contains("Ready to submit: no"));
+    }
+
+    #[test]
+    fn describes_guardian_pause_without_cosigners() {
+        let keys = keys();
+        let report = describe_op(
+            AdminOp::Pause {
+                flags: 0b10,
+                reason_code: 7,
+                guardian: true,
+            },
+            &keys,
+        );
+        assert!(report.contains("pause [purchase] (flags 0b10, reason code 7)"));
+        assert!(report.contains("(guardian_set) [writable]"));
+        assert!(!report.contains(&keys.cosigners[0].to_string()));
+    }
+
+    #[test]
+    fn describes_add_to_blacklist() {
+        let address = Pubkey::new_unique();
+        let keys = keys();
+        let report = describe_op(AdminOp::AddToBlacklist { address }, &keys);
+        assert!(report.contains(&format!("add_to_blacklist {address}")));
+        assert!(report.contains(&format!("{} [signer]", keys.cosigners[0])));
+        assert!(report.contains("(blacklist) [writable]"));
+        assert!(report.contains("(event_authority) []"));
+        assert!(report.contains("(dreamt program) []"));
+    }
+
+    #[test]
+    fn describes_update_max_supply() {
+        let keys = keys();
+        let report = describe_op(AdminOp::UpdateMaxSupply { new_max_supply: 90_000_000 }, &keys);
+        assert!(report.contains("update_max_supply to 90000000"));
+        assert!(report.contains(&format!("  {}: missing", keys.cosigners[0])));
+    }
+
+    #[test]
+    fn describes_admin_burn_with_refund() {
+        let keys = keys();
+        let accounts = BurnAccounts {
+            mint: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            reserve_vault: Pubkey::new_unique(),
+            refund_token: Pubkey::new_unique(),
+            recipient: Pubkey::new_unique(),
+            token_program: anchor_spl::token_2022::ID,
+            payment_token_program: anchor_spl::token::ID,
+        };
+        let report = describe_op(
+            AdminOp::AdminBurn {
+                amount: 5,
+                accounts: accounts.clone(),
+            },
+            &keys,
+        );
+        assert!(report.contains("admin_burn 5 tokens (refund 4.000000 USDC)"));
+        assert!(report.contains("(vault_owner) []"));
+        assert!(report.contains("(reserve_config) []"));
+        assert!(report.contains("(treasury) []"));
+        assert!(report.contains(&format!("{} [signer]", keys.cosigners[0])));
+
+        let overflow = AdminOp::AdminBurn {
+            amount: u64::MAX,
+            accounts,
+        };
+        assert!(describe_op(overflow, &keys).contains("(refund overflows)"));
+    }
+
+    #[test]
+    fn reports_ready_once_fully_signed() {
+        let authority = Keypair::new();
+        let keys = AdminKeys {
+            authority: authority.pubkey(),
+            multisig: Pubkey::new_unique(),
+            cosigners: Vec::new(),
+        };
+        let op = AdminOp::UpdateMaxSupply { new_max_supply: 1 };
+        let mut transaction = build(&[op.instruction(&keys)], &keys.authority, Hash::new_unique());
+        sign(&mut transaction, &authority).unwrap();
+        let report = describe(&transaction).unwrap();
+        assert!(report.contains(&format!("  {}: signed", authority.pubkey())));
+        assert!(report.contains("Ready to submit: yes"));
+    }
+}
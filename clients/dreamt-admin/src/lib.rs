@@ -0,0 +1,12 @@
+// DREAMT Admin Tool
+// Offline transaction building for multisig-approved admin operations
+//
+// Flow:
+// - build: write an unsigned transaction for an admin operation to a file
+// - sign: each signer adds their signature offline
+// - merge: combine the partially signed copies
+// - inspect: print the transaction's intent and signature state before submission
+
+pub mod inspect;
+pub mod ops;
+pub mod transaction;
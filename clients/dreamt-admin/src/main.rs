@@ -0,0 +1,199 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use clap::{Args, Parser, Subcommand};
+use dreamt_admin::ops::{parse_pause_flags, AdminKeys, AdminOp, BurnAccounts};
+use dreamt_admin::{inspect, transaction};
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::read_keypair_file;
+
+/// Offline transaction builder for DREAMT admin operations
+#[derive(Parser)]
+#[command(name = "dreamt-admin", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Build an unsigned transaction for an admin operation
+    Build(Box<BuildArgs>),
+    /// Add a signature to a transaction file
+    Sign {
+        /// Transaction file
+        file: PathBuf,
+        /// Signer keypair file
+        #[arg(long)]
+        keypair: PathBuf,
+        /// Output file, defaults to signing in place
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Merge partially signed copies of a transaction
+    Merge {
+        /// Transaction files
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Output file
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Print a transaction's intent and signature state
+    Inspect {
+        /// Transaction file
+        file: PathBuf,
+    },
+}
+
+#[derive(Args)]
+struct BuildArgs {
+    /// Account paying the transaction fee
+    #[arg(long)]
+    fee_payer: Pubkey,
+    /// Recent blockhash, fetched by the operator
+    #[arg(long)]
+    blockhash: Hash,
+    /// Admin (or guardian for pause) signing the operation
+    #[arg(long)]
+    authority: Pubkey,
+    /// Multisig recorded in TokenState
+    #[arg(long)]
+    multisig: Pubkey,
+    /// Multisig signer, may be repeated; ignored by a guardian pause
+    #[arg(long = "signer")]
+    cosigners: Vec<Pubkey>,
+    /// Output file
+    #[arg(long)]
+    out: PathBuf,
+    #[command(subcommand)]
+    operation: Operation,
+}
+
+#[derive(Subcommand)]
+enum Operation {
+    /// Pause operations
+    Pause {
+        /// Comma separated flags: mint, purchase, burn, redeem, transfer, admin-config, all
+        #[arg(long)]
+        flags: String,
+        /// Reason code recorded for guardian pauses
+        #[arg(long, default_value_t = 0)]
+        reason_code: u16,
+        /// Pause as a guardian instead of through the multisig
+        #[arg(long)]
+        guardian: bool,
+    },
+    /// Add an address to the blacklist
+    AddToBlacklist {
+        #[arg(long)]
+        address: Pubkey,
+    },
+    /// Lower the max supply
+    UpdateMaxSupply {
+        #[arg(long)]
+        new_max_supply: u64,
+    },
+    /// Burn vault tokens and refund USDC from the reserve
+    AdminBurn {
+        #[arg(long)]
+        amount: u64,
+        #[arg(long)]
+        mint: Pubkey,
+        /// DREAMT vault owned by the vault_owner PDA
+        #[arg(long)]
+        vault: Pubkey,
+        /// USDC reserve vault recorded in ReserveConfig
+        #[arg(long)]
+        reserve_vault: Pubkey,
+        /// USDC mint
+        #[arg(long)]
+        refund_token: Pubkey,
+        /// Registered treasury destination receiving the refund
+        #[arg(long)]
+        recipient: Pubkey,
+        #[arg(long, default_value_t = anchor_spl::token_2022::ID)]
+        token_program: Pubkey,
+        #[arg(long, default_value_t = anchor_spl::token::ID)]
+        payment_token_program: Pubkey,
+    },
+}
+
+impl Operation {
+    fn into_admin_op(self) -> Result<AdminOp> {
+        Ok(match self {
+            Operation::Pause {
+                flags,
+                reason_code,
+                guardian,
+            } => AdminOp::Pause {
+                flags: parse_pause_flags(&flags)?,
+                reason_code,
+                guardian,
+            },
+            Operation::AddToBlacklist { address } => AdminOp::AddToBlacklist { address },
+            Operation::UpdateMaxSupply { new_max_supply } => {
+                AdminOp::UpdateMaxSupply { new_max_supply }
+            }
+            Operation::AdminBurn {
+                amount,
+                mint,
+                vault,
+                reserve_vault,
+                refund_token,
+                recipient,
+                token_program,
+                payment_token_program,
+            } => AdminOp::AdminBurn {
+                amount,
+                accounts: BurnAccounts {
+                    mint,
+                    vault,
+                    reserve_vault,
+                    refund_token,
+                    recipient,
+                    token_program,
+                    payment_token_program,
+                },
+            },
+        })
+    }
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::Build(args) => {
+            let keys = AdminKeys {
+                authority: args.authority,
+                multisig: args.multisig,
+                cosigners: args.cosigners,
+            };
+            let instruction = args.operation.into_admin_op()?.instruction(&keys);
+            let transaction = transaction::build(&[instruction], &args.fee_payer, args.blockhash);
+            transaction::write(&args.out, &transaction)?;
+            print!("{}", inspect::describe(&transaction)?);
+        }
+        Command::Sign { file, keypair, out } => {
+            let keypair = read_keypair_file(&keypair)
+                .map_err(|err| anyhow!("reading {}: {err}", keypair.display()))?;
+            let mut transaction = transaction::read(&file)?;
+            transaction::sign(&mut transaction, &keypair)?;
+            transaction::write(out.as_ref().unwrap_or(&file), &transaction)?;
+            print!("{}", inspect::describe(&transaction)?);
+        }
+        Command::Merge { files, out } => {
+            let transactions = files
+                .iter()
+                .map(|file| transaction::read(file))
+                .collect::<Result<Vec<_>>>()?;
+            let transaction = transaction::merge(transactions)?;
+            transaction::write(&out, &transaction)?;
+            print!("{}", inspect::describe(&transaction)?);
+        }
+        Command::Inspect { file } => {
+            print!("{}", inspect::describe(&transaction::read(&file)?)?);
+        }
+    }
+    Ok(())
+}
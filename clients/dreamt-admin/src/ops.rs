@@ -0,0 +1,169 @@
+//! Admin operations
+//! Instructions for the operations approved by the multisig signers
+
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use anyhow::{bail, Result};
+use dreamt_client::constants::*;
+use dreamt_client::{dreamt, instructions, pda};
+
+/// Keys shared by every admin operation
+#[derive(Clone, Debug)]
+pub struct AdminKeys {
+    pub authority: Pubkey,        // admin or guardian signing the operation
+    pub multisig: Pubkey,         // multisig recorded in TokenState
+    pub cosigners: Vec<Pubkey>,   // multisig signers, not read by a guardian pause
+}
+
+/// Accounts needed by admin_burn that cannot be derived offline
+#[derive(Clone, Debug)]
+pub struct BurnAccounts {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub reserve_vault: Pubkey,
+    pub refund_token: Pubkey,
+    pub recipient: Pubkey,
+    pub token_program: Pubkey,
+    pub payment_token_program: Pubkey,
+}
+
+/// Admin operation to build a transaction for
+#[derive(Clone, Debug)]
+pub enum AdminOp {
+    Pause {
+        flags: u8,
+        reason_code: u16,
+        guardian: bool,
+    },
+    AddToBlacklist {
+        address: Pubkey,
+    },
+    UpdateMaxSupply {
+        new_max_supply: u64,
+    },
+    AdminBurn {
+        amount: u64,
+        accounts: BurnAccounts,
+    },
+}
+
+impl AdminOp {
+    /// Build the program instruction
+    /// The authority is a required signer; every multisig operation also carries the cosigners
+    /// as signing remaining accounts, which the program counts against the multisig threshold.
+    /// A guardian pause does not read them on chain, so they are left off that instruction
+    pub fn instruction(&self, keys: &AdminKeys) -> Instruction {
+        let token_state = pda::token_state().0;
+        let event_authority = pda::event_authority().0;
+
+        let mut instruction = match self {
+            AdminOp::Pause {
+                flags,
+                reason_code,
+                guardian,
+            } => instructions::pause(
+                dreamt::accounts::Pause {
+                    authority: keys.authority,
+                    token_state,
+                    multisig: keys.multisig,
+                    guardian_set: guardian.then(|| pda::guardian_set().0),
+                    event_authority,
+                    program: dreamt::ID,
+                },
+                *flags,
+                *reason_code,
+            ),
+            AdminOp::AddToBlacklist { address } => instructions::add_to_blacklist(
+                dreamt::accounts::UpdateBlacklist {
+                    authority: keys.authority,
+                    token_state,
+                    blacklist: pda::blacklist().0,
+                    multisig: keys.multisig,
+                    event_authority,
+                    program: dreamt::ID,
+                },
+                *address,
+            ),
+            AdminOp::UpdateMaxSupply { new_max_supply } => instructions::update_max_supply(
+                dreamt::accounts::UpdateMaxSupply {
+                    authority: keys.authority,
+                    token_state,
+                    multisig: keys.multisig,
+                    event_authority,
+                    program: dreamt::ID,
+                },
+                *new_max_supply,
+            ),
+            AdminOp::AdminBurn { amount, accounts } => instructions::admin_burn(
+                dreamt::accounts::AdminBurn {
+                    admin: keys.authority,
+                    token_state,
+                    multisig: keys.multisig,
+                    mint: accounts.mint,
+                    vault_owner: pda::vault_owner().0,
+                    vault: accounts.vault,
+                    reserve_config: pda::reserve_config().0,
+                    reserve_vault: accounts.reserve_vault,
+                    refund_token: accounts.refund_token,
+                    treasury: pda::treasury().0,
+                    recipient: accounts.recipient,
+                    token_program: accounts.token_program,
+                    payment_token_program: accounts.payment_token_program,
+                    event_authority,
+                    program: dreamt::ID,
+                },
+                *amount,
+            ),
+        };
+
+        for meta in instruction
+            .accounts
+            .iter_mut()
+            .filter(|meta| meta.pubkey == keys.authority)
+        {
+            meta.is_signer = true;
+        }
+        if !matches!(self, AdminOp::Pause { guardian: true, .. }) {
+            instruction.accounts.extend(
+                keys.cosigners
+                    .iter()
+                    .map(|cosigner| AccountMeta::new_readonly(*cosigner, true)),
+            );
+        }
+        instruction
+    }
+}
+
+/// Parse comma separated pause flag names into a bit mask
+pub fn parse_pause_flags(names: &str) -> Result<u8> {
+    let mut flags = 0;
+    for name in names.split(',').map(str::trim) {
+        flags |= match name {
+            "mint" => PAUSE_MINT,
+            "purchase" => PAUSE_PURCHASE,
+            "burn" => PAUSE_BURN,
+            "redeem" => PAUSE_REDEEM,
+            "transfer" => PAUSE_TRANSFER,
+            "admin-config" => PAUSE_ADMIN_CONFIG,
+            "all" => PAUSE_ALL,
+            _ => bail!("unknown pause flag `{name}`"),
+        };
+    }
+    Ok(flags)
+}
+
+/// Names of the pause flags set in a bit mask
+pub fn pause_flag_names(flags: u8) -> Vec<&'static str> {
+    [
+        (PAUSE_MINT, "mint"),
+        (PAUSE_PURCHASE, "purchase"),
+        (PAUSE_BURN, "burn"),
+        (PAUSE_REDEEM, "redeem"),
+        (PAUSE_TRANSFER, "transfer"),
+        (PAUSE_ADMIN_CONFIG, "admin-config"),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| flags & flag != 0)
+    .map(|(_, name)| name)
+    .collect()
+}
@@ -0,0 +1,246 @@
+//! Offline transactions
+//! Build, sign, merge and store transactions without touching the network
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sanitize::Sanitize;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+
+/// Signature state of one required signer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureStatus {
+    Missing,
+    Valid,
+    Invalid,
+}
+
+/// Build an unsigned transaction
+/// The blockhash is supplied by the operator, so the transaction must be submitted before it expires
+pub fn build(instructions: &[Instruction], fee_payer: &Pubkey, blockhash: Hash) -> Transaction {
+    let message = Message::new_with_blockhash(instructions, Some(fee_payer), &blockhash);
+    Transaction::new_unsigned(message)
+}
+
+/// Required signers of a transaction, fee payer first
+pub fn required_signers(transaction: &Transaction) -> &[Pubkey] {
+    let count = transaction.message.header.num_required_signatures as usize;
+    &transaction.message.account_keys[..count]
+}
+
+/// Add one signer's signature, leaving the others untouched
+pub fn sign(transaction: &mut Transaction, keypair: &Keypair) -> Result<()> {
+    ensure!(
+        required_signers(transaction).contains(&keypair.pubkey()),
+        "{} is not a required signer of this transaction",
+        keypair.pubkey()
+    );
+    let blockhash = transaction.message.recent_blockhash;
+    transaction.try_partial_sign(&[keypair], blockhash)?;
+    Ok(())
+}
+
+/// Merge partially signed copies of the same transaction
+/// Every copy must carry the same message; conflicting signatures are rejected
+pub fn merge(transactions: Vec<Transaction>) -> Result<Transaction> {
+    let mut transactions = transactions.into_iter();
+    let Some(mut merged) = transactions.next() else {
+        bail!("no transactions to merge");
+    };
+    let message_data = merged.message_data();
+
+    for transaction in transactions {
+        ensure!(
+            transaction.message_data() == message_data,
+            "transactions carry different messages"
+        );
+        for (index, signature) in transaction.signatures.into_iter().enumerate() {
+            if signature == Signature::default() {
+                continue;
+            }
+            let existing = &mut merged.signatures[index];
+            ensure!(
+                *existing == Signature::default() || *existing == signature,
+                "conflicting signatures for {}",
+                merged.message.account_keys[index]
+            );
+            *existing = signature;
+        }
+    }
+    Ok(merged)
+}
+
+/// Signature state of every required signer
+pub fn signature_statuses(transaction: &Transaction) -> Vec<(Pubkey, SignatureStatus)> {
+    let verified = transaction.verify_with_results();
+    required_signers(transaction)
+        .iter()
+        .zip(transaction.signatures.iter().zip(verified))
+        .map(|(signer, (signature, valid))| {
+            let status = if *signature == Signature::default() {
+                SignatureStatus::Missing
+            } else if valid {
+                SignatureStatus::Valid
+            } else {
+                SignatureStatus::Invalid
+            };
+            (*signer, status)
+        })
+        .collect()
+}
+
+/// Encode a transaction as base64 wire bytes
+pub fn encode(transaction: &Transaction) -> Result<String> {
+    Ok(STANDARD.encode(bincode::serialize(transaction)?))
+}
+
+/// Decode a transaction from base64 wire bytes
+pub fn decode(encoded: &str) -> Result<Transaction> {
+    let bytes = STANDARD
+        .decode(encoded.trim())
+        .context("transaction is not valid base64")?;
+    let transaction: Transaction =
+        bincode::deserialize(&bytes).context("transaction does not deserialize")?;
+    transaction.sanitize().context("transaction is malformed")?;
+    ensure!(
+        transaction.signatures.len() == transaction.message.header.num_required_signatures as usize,
+        "transaction signature count does not match its message"
+    );
+    Ok(transaction)
+}
+
+/// Read a transaction file
+pub fn read(path: &Path) -> Result<Transaction> {
+    let encoded =
+        fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    decode(&encoded).with_context(|| format!("decoding {}", path.display()))
+}
+
+/// Write a transaction file
+pub fn write(path: &Path, transaction: &Transaction) -> Result<()> {
+    fs::write(path, encode(transaction)? + "\n")
+        .with_context(|| format!("writing {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::{AdminKeys, AdminOp};
+
+    /// Helper function to build a multisig pause needing the authority and one cosigner
+    fn pause_transaction(authority: &Keypair, cosigner: &Keypair) -> Transaction {
+        let keys = AdminKeys {
+            authority: authority.pubkey(),
+            multisig: Pubkey::new_unique(),
+            cosigners: vec![cosigner.pubkey()],
+        };
+        let op = AdminOp::Pause {
+            flags: 1,
+            reason_code: 0,
+            guardian: false,
+        };
+        build(&[op.instruction(&keys)], &authority.pubkey(), Hash::new_unique())
+    }
+
+    #[test]
+    fn build_sign_merge_completes_signatures() {
+        let authority = Keypair::new();
+        let cosigner = Keypair::new();
+        let unsigned = pause_transaction(&authority, &cosigner);
+        assert_eq!(
+            required_signers(&unsigned),
+            &[authority.pubkey(), cosigner.pubkey()]
+        );
+
+        let mut first = unsigned.clone();
+        sign(&mut first, &authority).unwrap();
+        let mut second = unsigned;
+        sign(&mut second, &cosigner).unwrap();
+        assert_eq!(
+            signature_statuses(&first),
+            vec![
+                (authority.pubkey(), SignatureStatus::Valid),
+                (cosigner.pubkey(), SignatureStatus::Missing),
+            ]
+        );
+
+        let merged = merge(vec![first, second]).unwrap();
+        assert!(signature_statuses(&merged)
+            .iter()
+            .all(|(_, status)| *status == SignatureStatus::Valid));
+        merged.verify().unwrap();
+    }
+
+    #[test]
+    fn sign_rejects_foreign_keypair() {
+        let unsigned = pause_transaction(&Keypair::new(), &Keypair::new());
+        let mut transaction = unsigned.clone();
+        assert!(sign(&mut transaction, &Keypair::new()).is_err());
+        assert_eq!(transaction, unsigned);
+    }
+
+    #[test]
+    fn merge_rejects_conflicting_signatures() {
+        let authority = Keypair::new();
+        let unsigned = pause_transaction(&authority, &Keypair::new());
+        let mut signed = unsigned.clone();
+        sign(&mut signed, &authority).unwrap();
+        let mut forged = unsigned;
+        forged.signatures[0] = Signature::from([7; 64]);
+
+        let err = merge(vec![signed, forged]).unwrap_err();
+        assert!(err.to_string().contains("conflicting signatures"));
+    }
+
+    #[test]
+    fn merge_flags_foreign_signature_as_invalid() {
+        let authority = Keypair::new();
+        let cosigner = Keypair::new();
+        let unsigned = pause_transaction(&authority, &cosigner);
+        let mut signed = unsigned.clone();
+        sign(&mut signed, &authority).unwrap();
+        // A signature over the right message by a key that is not the cosigner
+        let mut foreign = unsigned;
+        foreign.signatures[1] = Keypair::new().sign_message(&foreign.message_data());
+
+        let merged = merge(vec![signed, foreign]).unwrap();
+        assert_eq!(
+            signature_statuses(&merged),
+            vec![
+                (authority.pubkey(), SignatureStatus::Valid),
+                (cosigner.pubkey(), SignatureStatus::Invalid),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_rejects_different_messages() {
+        let authority = Keypair::new();
+        let cosigner = Keypair::new();
+        let first = pause_transaction(&authority, &cosigner);
+        let second = pause_transaction(&authority, &cosigner);
+        let err = merge(vec![first, second]).unwrap_err();
+        assert!(err.to_string().contains("different messages"));
+        assert!(merge(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn decode_round_trips_and_rejects_malformed_transactions() {
+        let authority = Keypair::new();
+        let mut transaction = pause_transaction(&authority, &Keypair::new());
+        sign(&mut transaction, &authority).unwrap();
+        assert_eq!(decode(&encode(&transaction).unwrap()).unwrap(), transaction);
+
+        let mut malformed = transaction;
+        malformed.message.instructions[0].program_id_index = u8::MAX;
+        assert!(decode(&encode(&malformed).unwrap()).is_err());
+        assert!(decode("not base64!").is_err());
+    }
+}
@@ -0,0 +1,40 @@
+//! Account deserializers
+//! Check the Anchor discriminator and reject accounts still awaiting migrate_state
+
+use anchor_lang::prelude::*;
+use dreamt::constants::STATE_VERSION;
+use dreamt::error::DiamondTokenError;
+use dreamt::state::{Blacklist, LegacyBlacklist, LegacyTokenState, TokenState};
+
+/// Deserialize any DREAMT account from raw account data
+pub fn deserialize<T: AccountDeserialize>(data: &[u8]) -> Result<T> {
+    T::try_deserialize(&mut &data[..])
+}
+
+/// Deserialize the TokenState account
+pub fn token_state(data: &[u8]) -> Result<TokenState> {
+    require!(
+        data.len() != LegacyTokenState::SPACE,
+        DiamondTokenError::StateNotMigrated
+    );
+    let token_state: TokenState = deserialize(data)?;
+    require!(
+        token_state.version == STATE_VERSION,
+        DiamondTokenError::StateNotMigrated
+    );
+    Ok(token_state)
+}
+
+/// Deserialize the Blacklist account
+pub fn blacklist(data: &[u8]) -> Result<Blacklist> {
+    require!(
+        data.len() != LegacyBlacklist::SPACE,
+        DiamondTokenError::StateNotMigrated
+    );
+    let blacklist: Blacklist = deserialize(data)?;
+    require!(
+        blacklist.version == STATE_VERSION,
+        DiamondTokenError::StateNotMigrated
+    );
+    Ok(blacklist)
+}
@@ -0,0 +1,121 @@
+//! Event decoders
+//! Events are emitted through a self-CPI; decode them from the inner instruction data
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::prelude::*;
+use anchor_lang::{Discriminator, Event};
+use dreamt::events::*;
+
+macro_rules! dreamt_events {
+    ($($event:ident),* $(,)?) => {
+        /// Any event emitted by the DREAMT program
+        pub enum DreamtEvent {
+            $($event($event),)*
+        }
+
+        impl DreamtEvent {
+            /// Event name, as used in the IDL
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(DreamtEvent::$event(_) => stringify!($event),)*
+                }
+            }
+
+            /// Program-wide sequence number; consecutive events differ by exactly one
+            pub fn sequence(&self) -> u64 {
+                match self {
+                    $(DreamtEvent::$event(event) => event.sequence,)*
+                }
+            }
+
+            /// Slot in which the event was emitted
+            pub fn slot(&self) -> u64 {
+                match self {
+                    $(DreamtEvent::$event(event) => event.slot,)*
+                }
+            }
+
+            /// Discriminator-prefixed event data, as emitted by the program
+            pub fn data(&self) -> Vec<u8> {
+                match self {
+                    $(DreamtEvent::$event(event) => event.data(),)*
+                }
+            }
+        }
+
+        /// Decode an event from its discriminator-prefixed data
+        /// Returns None for an unknown discriminator
+        pub fn decode_event(data: &[u8]) -> Result<Option<DreamtEvent>> {
+            $(
+                if let Some(mut body) = data.strip_prefix($event::DISCRIMINATOR) {
+                    let event = $event::deserialize(&mut body)
+                        .map_err(|_| error!(ErrorCode::InstructionDidNotDeserialize))?;
+                    return Ok(Some(DreamtEvent::$event(event)));
+                }
+            )*
+            Ok(None)
+        }
+    };
+}
+
+dreamt_events! {
+    TokenStateInitialized,
+    TokensMinted,
+    TokensBurned,
+    ProgramPaused,
+    ProgramUnpaused,
+    MaxSupplyUpdated,
+    BlacklistUpdated,
+    ItemPurchased,
+    OrderCreated,
+    OrderStatusUpdated,
+    PurchaseRefunded,
+    MerchantUpdated,
+    CatalogManagerUpdated,
+    CatalogItemUpdated,
+    EscrowConfigUpdated,
+    EscrowFunded,
+    EscrowDisputed,
+    EscrowSettled,
+    CartLinePurchased,
+    CartPurchased,
+    MarketplaceConfigUpdated,
+    MerchantRegistered,
+    MerchantStatusUpdated,
+    MarketplaceSale,
+    MarketingRoleUpdated,
+    CouponCreated,
+    CouponRedeemed,
+    LoyaltyRatesUpdated,
+    PointsIssued,
+    PointsRedeemed,
+    PointsRevoked,
+    PointsCreditUsed,
+    StakingConfigured,
+    Staked,
+    UnstakeRequested,
+    StakeWithdrawn,
+    StakeDiscountApplied,
+    ReferralConfigUpdated,
+    ReferrerRegistered,
+    ReferralRewarded,
+    PurchaseIntentExecuted,
+    GuardiansUpdated,
+    GuardianPaused,
+    StateMigrated,
+    ReserveVerified,
+    ReserveShortfall,
+    ReserveConfigUpdated,
+    TreasuryDestinationUpdated,
+    TreasuryWithdrawn,
+}
+
+/// Decode an event from the data of a DREAMT inner instruction
+/// Returns None for inner instructions that are not event self-CPIs
+pub fn decode_event_cpi(instruction_data: &[u8]) -> Result<Option<DreamtEvent>> {
+    match instruction_data.strip_prefix(EVENT_IX_TAG_LE) {
+        Some(data) => decode_event(data),
+        None => Ok(None),
+    }
+}
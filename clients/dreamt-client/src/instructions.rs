@@ -0,0 +1,161 @@
+//! Typed instruction builders
+//! Each builder takes the instruction's account struct and arguments and returns a ready Instruction
+
+use anchor_lang::prelude::{AccountMeta, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use dreamt::accounts;
+use dreamt::constants::STAKE_TIER_COUNT;
+use dreamt::instruction as ix;
+use dreamt::state::{
+    CartLine, DiscountKind, OrderStatus, PriceCurrency, PurchaseIntent, StakeTier,
+};
+
+use crate::pda;
+
+/// Helper function to assemble a DREAMT instruction
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: dreamt::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+macro_rules! instruction_builders {
+    ($($name:ident($accounts:ident) => $data:ident { $($arg:ident: $ty:ty),* $(,)? };)*) => {
+        $(
+            #[allow(clippy::too_many_arguments)]
+            pub fn $name(accounts: accounts::$accounts, $($arg: $ty),*) -> Instruction {
+                build(accounts, ix::$data { $($arg),* })
+            }
+        )*
+    };
+}
+
+instruction_builders! {
+    initialize(Initialize) => Initialize { multisig_owners: Vec<Pubkey>, threshold: u64 };
+    mint_by_user(MintByUser) => MintByUser { amount: u64, max_payment: u64 };
+    mint_by_user_exact_in(MintByUser) => MintByUserExactIn { payment_amount: u64, min_tokens_out: u64 };
+    mint_with_intent(MintWithIntent) => MintWithIntent { intent: PurchaseIntent };
+    admin_burn(AdminBurn) => AdminBurn { amount: u64 };
+    migrate_state(MigrateState) => MigrateState {};
+    pause(Pause) => Pause { flags: u8, reason_code: u16 };
+    set_guardians(SetGuardians) => SetGuardians { guardians: Vec<Pubkey> };
+    unpause(Unpause) => Unpause { flags: u8 };
+    update_max_supply(UpdateMaxSupply) => UpdateMaxSupply { new_max_supply: u64 };
+    add_to_blacklist(UpdateBlacklist) => AddToBlacklist { address: Pubkey };
+    remove_from_blacklist(UpdateBlacklist) => RemoveFromBlacklist { address: Pubkey };
+    purchase_item(PurchaseItem) => PurchaseItem {
+        amount: u64,
+        item_id: String,
+        shipping_info_hash: [u8; 32],
+        coupon_code: Option<String>,
+    };
+    purchase_item_with_usdc(PurchaseItemWithUsdc) => PurchaseItemWithUsdc {
+        item_id: String,
+        shipping_info_hash: [u8; 32],
+    };
+    purchase_marketplace_item(PurchaseMarketplaceItem) => PurchaseMarketplaceItem {
+        amount: u64,
+        item_id: String,
+        shipping_info_hash: [u8; 32],
+    };
+    configure_marketplace(ConfigureMarketplace) => ConfigureMarketplace { platform_fee_bps: u64 };
+    register_merchant(RegisterMerchant) => RegisterMerchant { merchant_authority: Pubkey };
+    set_merchant_active(SetMerchantActive) => SetMerchantActive { is_active: bool };
+    purchase_item_escrowed(PurchaseItemEscrowed) => PurchaseItemEscrowed {
+        amount: u64,
+        item_id: String,
+        shipping_info_hash: [u8; 32],
+    };
+    configure_escrow(ConfigureEscrow) => ConfigureEscrow { arbitrator: Pubkey, release_timeout: i64 };
+    release_escrow(ReleaseEscrow) => ReleaseEscrow {};
+    raise_dispute(RaiseDispute) => RaiseDispute {};
+    resolve_dispute(ResolveDispute) => ResolveDispute { refund_amount: u64 };
+    set_marketing_role(SetMarketingRole) => SetMarketingRole { marketing: Pubkey };
+    create_coupon(CreateCoupon) => CreateCoupon {
+        code_hash: [u8; 32],
+        kind: DiscountKind,
+        value: u64,
+        max_uses: u32,
+        expires_at: i64,
+        item_id: Option<String>,
+        redeemer: Pubkey,
+    };
+    initialize_loyalty(InitializeLoyalty) => InitializeLoyalty {
+        purchase_rate_bps: u64,
+        mint_rate_bps: u64,
+        points_per_credit: u64,
+    };
+    update_loyalty_rates(UpdateLoyaltyRates) => UpdateLoyaltyRates {
+        purchase_rate_bps: u64,
+        mint_rate_bps: u64,
+        points_per_credit: u64,
+    };
+    redeem_points(RedeemPoints) => RedeemPoints { points: u64 };
+    configure_staking(ConfigureStaking) => ConfigureStaking {
+        unbonding_period: i64,
+        tiers: [StakeTier; STAKE_TIER_COUNT],
+    };
+    stake(StakeTokens) => Stake { amount: u64 };
+    request_unstake(RequestUnstake) => RequestUnstake { amount: u64 };
+    withdraw_stake(WithdrawStake) => WithdrawStake {};
+    configure_referrals(ConfigureReferrals) => ConfigureReferrals { reward_bps: u64 };
+    register_referrer(RegisterReferrer) => RegisterReferrer { referrer: Pubkey };
+    set_merchant(SetMerchant) => SetMerchant { merchant: Pubkey };
+    update_order_status(UpdateOrderStatus) => UpdateOrderStatus { new_status: OrderStatus };
+    set_catalog_manager(SetCatalogManager) => SetCatalogManager { manager: Pubkey };
+    create_catalog_item(CreateCatalogItem) => CreateCatalogItem {
+        item_id: String,
+        price: u64,
+        price_currency: PriceCurrency,
+        stock: u64,
+        merchant: Pubkey,
+    };
+    update_catalog_item(UpdateCatalogItem) => UpdateCatalogItem {
+        price: u64,
+        price_currency: PriceCurrency,
+        stock: u64,
+        is_active: bool,
+        merchant: Pubkey,
+    };
+    remove_catalog_item(RemoveCatalogItem) => RemoveCatalogItem {};
+    refund_purchase(RefundPurchase) => RefundPurchase { amount: u64 };
+    on_transfer_hook(TransferHook) => OnTransferHook { amount: u64 };
+    verify_reserve(VerifyReserve) => VerifyReserve {};
+    configure_reserve(ConfigureReserve) => ConfigureReserve { min_reserve_ratio_bps: u64 };
+    set_treasury_destination(SetTreasuryDestination) => SetTreasuryDestination {};
+    withdraw_treasury(WithdrawTreasury) => WithdrawTreasury { amount: u64 };
+    initialize_reserve_history(InitializeReserveHistory) => InitializeReserveHistory {};
+    get_reserve_history(GetReserveHistory) => GetReserveHistory { count: u8 };
+    quote_mint(ViewTokenState) => QuoteMint { amount: u64 };
+    quote_redeem(ViewTokenState) => QuoteRedeem { amount: u64 };
+    is_blacklisted(ViewBlacklist) => IsBlacklisted { address: Pubkey };
+    reserve_ratio(ViewReserveRatio) => ReserveRatio {};
+    remaining_mintable(ViewTokenState) => RemainingMintable {};
+    close_token_state(CloseTokenState) => CloseTokenState {};
+}
+
+/// Cart purchase; the catalog item of each line is passed as a writable remaining account
+pub fn purchase_items(
+    accounts: accounts::PurchaseItems,
+    lines: Vec<CartLine>,
+    total_amount: u64,
+    shipping_info_hash: [u8; 32],
+) -> Instruction {
+    let catalog_items: Vec<AccountMeta> = lines
+        .iter()
+        .map(|line| AccountMeta::new(pda::catalog_item(&line.item_id).0, false))
+        .collect();
+    let mut instruction = build(
+        accounts,
+        ix::PurchaseItems {
+            lines,
+            total_amount,
+            shipping_info_hash,
+        },
+    );
+    instruction.accounts.extend(catalog_items);
+    instruction
+}
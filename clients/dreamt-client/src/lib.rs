@@ -0,0 +1,17 @@
+// DREAMT Client SDK
+// Off-chain helpers for building transactions against the DREAMT program
+//
+// Modules:
+// - pda: PDA derivation for every program account
+// - instructions: typed instruction builders for every program instruction
+// - accounts: TokenState and Blacklist deserializers
+// - quote: price quotes built on the program's math module
+// - events: decoders for events emitted through the event self-CPI
+
+pub mod accounts;
+pub mod events;
+pub mod instructions;
+pub mod pda;
+pub mod quote;
+
+pub use dreamt::{self, constants, error, state, ID};
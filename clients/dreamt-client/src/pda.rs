@@ -0,0 +1,145 @@
+//! PDA derivation
+//! Each helper returns the address and bump, matching the seeds used by the program
+
+use anchor_lang::prelude::Pubkey;
+use dreamt::constants::*;
+
+fn find(seeds: &[&[u8]]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(seeds, &dreamt::ID)
+}
+
+/// Program state
+pub fn token_state() -> (Pubkey, u8) {
+    find(&[TOKEN_STATE_SEED])
+}
+
+pub fn blacklist() -> (Pubkey, u8) {
+    find(&[BLACKLIST_SEED])
+}
+
+pub fn guardian_set() -> (Pubkey, u8) {
+    find(&[GUARDIAN_SET_SEED])
+}
+
+/// Mint and vault authorities
+pub fn mint_authority() -> (Pubkey, u8) {
+    find(&[MINT_AUTHORITY_SEED])
+}
+
+pub fn vault_owner() -> (Pubkey, u8) {
+    find(&[VAULT_OWNER_SEED])
+}
+
+pub fn mint_delegate() -> (Pubkey, u8) {
+    find(&[MINT_DELEGATE_SEED])
+}
+
+/// Proof of reserve and treasury
+pub fn reserve_history() -> (Pubkey, u8) {
+    find(&[RESERVE_HISTORY_SEED])
+}
+
+pub fn reserve_config() -> (Pubkey, u8) {
+    find(&[RESERVE_CONFIG_SEED])
+}
+
+pub fn treasury() -> (Pubkey, u8) {
+    find(&[TREASURY_SEED])
+}
+
+/// Orders and catalog
+pub fn order_config() -> (Pubkey, u8) {
+    find(&[ORDER_CONFIG_SEED])
+}
+
+pub fn buyer_orders(buyer: &Pubkey) -> (Pubkey, u8) {
+    find(&[BUYER_ORDERS_SEED, buyer.as_ref()])
+}
+
+pub fn order(buyer: &Pubkey, order_number: u64) -> (Pubkey, u8) {
+    find(&[ORDER_SEED, buyer.as_ref(), &order_number.to_le_bytes()])
+}
+
+pub fn catalog_config() -> (Pubkey, u8) {
+    find(&[CATALOG_CONFIG_SEED])
+}
+
+pub fn catalog_item(item_id: &str) -> (Pubkey, u8) {
+    find(&[CATALOG_ITEM_SEED, item_id.as_bytes()])
+}
+
+/// Escrow
+pub fn escrow_config() -> (Pubkey, u8) {
+    find(&[ESCROW_CONFIG_SEED])
+}
+
+pub fn escrow(buyer: &Pubkey, order_number: u64) -> (Pubkey, u8) {
+    find(&[ESCROW_SEED, buyer.as_ref(), &order_number.to_le_bytes()])
+}
+
+pub fn escrow_vault(buyer: &Pubkey, order_number: u64) -> (Pubkey, u8) {
+    find(&[ESCROW_VAULT_SEED, buyer.as_ref(), &order_number.to_le_bytes()])
+}
+
+/// Marketplace
+pub fn marketplace_config() -> (Pubkey, u8) {
+    find(&[MARKETPLACE_CONFIG_SEED])
+}
+
+pub fn merchant(merchant_authority: &Pubkey) -> (Pubkey, u8) {
+    find(&[MERCHANT_SEED, merchant_authority.as_ref()])
+}
+
+/// Coupons
+pub fn coupon_config() -> (Pubkey, u8) {
+    find(&[COUPON_CONFIG_SEED])
+}
+
+pub fn coupon(code_hash: &[u8; 32]) -> (Pubkey, u8) {
+    find(&[COUPON_SEED, code_hash.as_ref()])
+}
+
+/// Loyalty
+pub fn loyalty_config() -> (Pubkey, u8) {
+    find(&[LOYALTY_CONFIG_SEED])
+}
+
+pub fn points_mint() -> (Pubkey, u8) {
+    find(&[POINTS_MINT_SEED])
+}
+
+pub fn points_credit(user: &Pubkey) -> (Pubkey, u8) {
+    find(&[POINTS_CREDIT_SEED, user.as_ref()])
+}
+
+/// Staking
+pub fn stake_config() -> (Pubkey, u8) {
+    find(&[STAKE_CONFIG_SEED])
+}
+
+pub fn stake(user: &Pubkey) -> (Pubkey, u8) {
+    find(&[STAKE_SEED, user.as_ref()])
+}
+
+pub fn stake_vault() -> (Pubkey, u8) {
+    find(&[STAKE_VAULT_SEED])
+}
+
+/// Referrals
+pub fn referral_config() -> (Pubkey, u8) {
+    find(&[REFERRAL_CONFIG_SEED])
+}
+
+pub fn referral(referrer: &Pubkey) -> (Pubkey, u8) {
+    find(&[REFERRAL_SEED, referrer.as_ref()])
+}
+
+/// Gasless purchase intents
+pub fn intent_nonce(user: &Pubkey) -> (Pubkey, u8) {
+    find(&[INTENT_NONCE_SEED, user.as_ref()])
+}
+
+/// Event self-CPI
+pub fn event_authority() -> (Pubkey, u8) {
+    find(&[EVENT_AUTHORITY_SEED])
+}
@@ -0,0 +1,57 @@
+//! Price quotes
+//! Built on the program's own pricing math so quotes match what the program will charge
+
+use anchor_lang::prelude::*;
+use dreamt::constants::*;
+use dreamt::error::DiamondTokenError;
+use dreamt::state::{StakeConfig, TokenState};
+
+pub use dreamt::math::{
+    calculate_expected_reserve, calculate_mint_purchase, calculate_refund_amount,
+    calculate_reserve_ratio_bps, calculate_stake_discount, calculate_tokens_for_payment,
+    stake_mint_limit,
+};
+
+/// Quote for a user mint
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MintQuote {
+    pub amount: u64,          // tokens minted
+    pub payment_amount: u64,  // USDC charged
+    pub new_supply: u64,      // total supply after minting
+}
+
+/// Quote mint_by_user for `amount` tokens
+/// Pass the caller's stake config and staked amount to apply their tier's purchase limit
+pub fn quote_mint(
+    token_state: &TokenState,
+    amount: u64,
+    stake: Option<(&StakeConfig, u64)>,
+) -> Result<MintQuote> {
+    require!(amount > 0, DiamondTokenError::InvalidAmount);
+
+    let max_purchase_usdc = match stake.and_then(|(config, staked)| config.tier_for(staked)) {
+        Some((_, tier)) => stake_mint_limit(tier)?,
+        None => MAX_PURCHASE_USDC,
+    };
+
+    let (payment_amount, new_supply) =
+        calculate_mint_purchase(token_state, amount, max_purchase_usdc)?;
+
+    Ok(MintQuote {
+        amount,
+        payment_amount,
+        new_supply,
+    })
+}
+
+/// Quote mint_by_user_exact_in for `payment_amount` USDC
+/// Rounds down so the buyer is never charged for a partial token
+pub fn quote_mint_exact_in(
+    token_state: &TokenState,
+    payment_amount: u64,
+    stake: Option<(&StakeConfig, u64)>,
+) -> Result<MintQuote> {
+    let amount = calculate_tokens_for_payment(payment_amount);
+    require!(amount > 0, DiamondTokenError::PurchaseAmountTooSmall);
+    quote_mint(token_state, amount, stake)
+}
@@ -0,0 +1,11 @@
+// DREAMT Event Indexer
+// Indexes DREAMT events from recorded transactions into SQLite
+//
+// Modules:
+// - transaction: getTransaction JSON parsing and event extraction (self-CPI or logs)
+// - store: SQLite schema and idempotent upserts keyed by (signature, event_index)
+// - queries: supply history, event sequence gaps, per-user purchases, blacklist history, reserve checks
+
+pub mod queries;
+pub mod store;
+pub mod transaction;
@@ -0,0 +1,152 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use dreamt_indexer::store::Store;
+use dreamt_indexer::transaction::RecordedTransaction;
+
+/// SQLite event indexer for the DREAMT program
+#[derive(Parser)]
+#[command(name = "dreamt-indexer", version)]
+struct Cli {
+    /// SQLite database file
+    #[arg(long, default_value = "dreamt-events.sqlite")]
+    db: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Index recorded getTransaction responses (JSON encoding)
+    Ingest {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Print the supply history
+    Supply,
+    /// Print event sequence ranges missing from the index
+    Gaps,
+    /// Print a user's purchases and totals
+    Purchases {
+        #[arg(long)]
+        user: String,
+    },
+    /// Print blacklist changes
+    Blacklist {
+        #[arg(long)]
+        address: Option<String>,
+    },
+    /// Print proof-of-reserve checks
+    Reserve {
+        #[arg(long)]
+        shortfalls_only: bool,
+    },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut store = Store::open(&cli.db)?;
+
+    match cli.command {
+        Command::Ingest { files } => {
+            for file in files {
+                let transaction = RecordedTransaction::read(&file)?;
+                let count = store.ingest(&transaction)?;
+                println!("{}: {count} events from {}", file.display(), transaction.signature()?);
+            }
+            for gap in store.sequence_gaps()? {
+                eprintln!(
+                    "warning: events {}..={} missing between slots {} and {}",
+                    gap.first_missing, gap.last_missing, gap.after_slot, gap.before_slot
+                );
+            }
+        }
+        Command::Supply => {
+            for point in store.supply_history()? {
+                println!(
+                    "slot {} seq {} {} {:+} by {} -> supply {} / max {} ({})",
+                    point.slot,
+                    point.sequence,
+                    point.kind,
+                    point.supply_delta,
+                    point.account,
+                    point.total_supply,
+                    point.max_supply.map_or("?".to_string(), |max| max.to_string()),
+                    point.signature
+                );
+            }
+        }
+        Command::Gaps => {
+            let gaps = store.sequence_gaps()?;
+            if gaps.is_empty() {
+                println!("No missing events");
+            }
+            for gap in gaps {
+                println!(
+                    "events {}..={} missing between slots {} and {}",
+                    gap.first_missing, gap.last_missing, gap.after_slot, gap.before_slot
+                );
+            }
+        }
+        Command::Purchases { user } => {
+            for purchase in store.user_purchases(&user)? {
+                println!(
+                    "slot {} seq {} {} amount {}{}{}{} ({})",
+                    purchase.slot,
+                    purchase.sequence,
+                    purchase.kind,
+                    purchase.amount,
+                    purchase
+                        .payment_amount
+                        .map_or(String::new(), |paid| format!(" paid {paid}")),
+                    purchase
+                        .item_id
+                        .map_or(String::new(), |item_id| format!(" item {item_id}")),
+                    purchase
+                        .order_number
+                        .map_or(String::new(), |order| format!(" order {order}")),
+                    purchase.signature
+                );
+            }
+            let totals = store.user_purchase_totals(&user)?;
+            println!(
+                "Totals: {} purchases, {} tokens spent, {} refunded, {} minted for {} USDC",
+                totals.purchases,
+                totals.tokens_spent,
+                totals.tokens_refunded,
+                totals.tokens_minted,
+                totals.usdc_paid
+            );
+        }
+        Command::Blacklist { address } => {
+            for change in store.blacklist_history(address.as_deref())? {
+                println!(
+                    "slot {} seq {} {} {} by {} ({})",
+                    change.slot,
+                    change.sequence,
+                    change.action,
+                    change.address,
+                    change.authority,
+                    change.signature
+                );
+            }
+        }
+        Command::Reserve { shortfalls_only } => {
+            for check in store.reserve_checks(shortfalls_only)? {
+                println!(
+                    "slot {} seq {} supply {} reserve {} ratio {} bps{}{} ({})",
+                    check.slot,
+                    check.sequence,
+                    check.total_supply,
+                    check.reserve_amount,
+                    check.ratio_bps.map_or("?".to_string(), |ratio| ratio.to_string()),
+                    if check.shortfall { " SHORTFALL" } else { "" },
+                    if check.paused { " paused" } else { "" },
+                    check.signature
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,223 @@
+//! Summary queries over indexed events
+//! Rows are returned in event sequence order
+
+use anyhow::Result;
+use rusqlite::{params, Row};
+
+use crate::store::Store;
+
+/// Supply after one supply-changing event
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SupplyPoint {
+    pub signature: String,
+    pub slot: u64,
+    pub sequence: u64,
+    pub kind: String,         // initialize, mint, burn or max_supply
+    pub account: String,      // user, admin or authority behind the change
+    pub supply_delta: i64,
+    pub total_supply: i64,
+    pub max_supply: Option<u64>,
+}
+
+/// One purchase-related event of a user
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Purchase {
+    pub signature: String,
+    pub slot: u64,
+    pub sequence: u64,
+    pub kind: String,         // mint, item, cart, marketplace, escrow or refund
+    pub item_id: Option<String>,
+    pub order_number: Option<u64>,
+    pub amount: u64,
+    pub payment_amount: Option<u64>,
+}
+
+/// Purchase totals of a user
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PurchaseTotals {
+    pub purchases: u64,
+    pub tokens_spent: u64,
+    pub tokens_refunded: u64,
+    pub tokens_minted: u64,
+    pub usdc_paid: u64,
+}
+
+/// One blacklist addition or removal
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlacklistChange {
+    pub signature: String,
+    pub slot: u64,
+    pub sequence: u64,
+    pub authority: String,
+    pub address: String,
+    pub action: String,       // added or removed
+}
+
+/// One proof-of-reserve check
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReserveCheck {
+    pub signature: String,
+    pub slot: u64,
+    pub sequence: u64,
+    pub total_supply: u64,
+    pub reserve_amount: u64,
+    pub ratio_bps: Option<u64>,
+    pub shortfall: bool,
+    pub paused: bool,
+}
+
+/// Run of event sequence numbers missing from the index
+/// The program numbers every event, so any hole in the indexed sequence is a missed event
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SequenceGap {
+    pub first_missing: u64,
+    pub last_missing: u64,
+    pub after_slot: u64,      // slot of the last indexed event before the gap, 0 at the start
+    pub before_slot: u64,     // slot of the first indexed event after the gap
+}
+
+impl Store {
+    /// Supply history with the running total supply and max supply
+    pub fn supply_history(&self) -> Result<Vec<SupplyPoint>> {
+        let mut statement = self.conn.prepare(
+            "SELECT signature, slot, sequence, kind, account, supply_delta, max_supply
+             FROM supply_changes ORDER BY sequence",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(SupplyPoint {
+                signature: row.get(0)?,
+                slot: get_u64(row, 1)?,
+                sequence: get_u64(row, 2)?,
+                kind: row.get(3)?,
+                account: row.get(4)?,
+                supply_delta: row.get(5)?,
+                total_supply: 0,
+                max_supply: row.get::<_, Option<i64>>(6)?.map(|max_supply| max_supply as u64),
+            })
+        })?;
+
+        let mut total_supply = 0;
+        let mut max_supply = None;
+        let mut history = Vec::new();
+        for row in rows {
+            let mut point = row?;
+            total_supply += point.supply_delta;
+            max_supply = point.max_supply.or(max_supply);
+            point.total_supply = total_supply;
+            point.max_supply = max_supply;
+            history.push(point);
+        }
+        Ok(history)
+    }
+
+    /// Missing event sequence ranges, from the first sequence number up to the latest indexed
+    /// event; an empty result means no event was missed
+    pub fn sequence_gaps(&self) -> Result<Vec<SequenceGap>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT DISTINCT sequence, slot FROM events ORDER BY sequence")?;
+        let rows = statement.query_map([], |row| Ok((get_u64(row, 0)?, get_u64(row, 1)?)))?;
+
+        let (mut previous, mut previous_slot) = (0, 0);
+        let mut gaps = Vec::new();
+        for row in rows {
+            let (sequence, slot) = row?;
+            if sequence > previous + 1 {
+                gaps.push(SequenceGap {
+                    first_missing: previous + 1,
+                    last_missing: sequence - 1,
+                    after_slot: previous_slot,
+                    before_slot: slot,
+                });
+            }
+            (previous, previous_slot) = (sequence, slot);
+        }
+        Ok(gaps)
+    }
+
+    /// Purchase-related events of a user
+    pub fn user_purchases(&self, user: &str) -> Result<Vec<Purchase>> {
+        let mut statement = self.conn.prepare(
+            "SELECT signature, slot, sequence, kind, item_id, order_number, amount, payment_amount
+             FROM purchases WHERE user = ?1 ORDER BY sequence",
+        )?;
+        let rows = statement.query_map(params![user], |row| {
+            Ok(Purchase {
+                signature: row.get(0)?,
+                slot: get_u64(row, 1)?,
+                sequence: get_u64(row, 2)?,
+                kind: row.get(3)?,
+                item_id: row.get(4)?,
+                order_number: row.get::<_, Option<i64>>(5)?.map(|number| number as u64),
+                amount: get_u64(row, 6)?,
+                payment_amount: row.get::<_, Option<i64>>(7)?.map(|amount| amount as u64),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Purchase totals of a user
+    pub fn user_purchase_totals(&self, user: &str) -> Result<PurchaseTotals> {
+        let mut totals = PurchaseTotals::default();
+        for purchase in self.user_purchases(user)? {
+            match purchase.kind.as_str() {
+                "mint" => {
+                    totals.tokens_minted += purchase.amount;
+                    totals.usdc_paid += purchase.payment_amount.unwrap_or_default();
+                }
+                "refund" => totals.tokens_refunded += purchase.amount,
+                _ => {
+                    totals.purchases += 1;
+                    totals.tokens_spent += purchase.amount;
+                }
+            }
+        }
+        Ok(totals)
+    }
+
+    /// Blacklist changes, optionally for one address
+    pub fn blacklist_history(&self, address: Option<&str>) -> Result<Vec<BlacklistChange>> {
+        let mut statement = self.conn.prepare(
+            "SELECT signature, slot, sequence, authority, address, action
+             FROM blacklist_changes WHERE ?1 IS NULL OR address = ?1 ORDER BY sequence",
+        )?;
+        let rows = statement.query_map(params![address], |row| {
+            Ok(BlacklistChange {
+                signature: row.get(0)?,
+                slot: get_u64(row, 1)?,
+                sequence: get_u64(row, 2)?,
+                authority: row.get(3)?,
+                address: row.get(4)?,
+                action: row.get(5)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Proof-of-reserve checks, optionally only shortfalls
+    pub fn reserve_checks(&self, shortfalls_only: bool) -> Result<Vec<ReserveCheck>> {
+        let mut statement = self.conn.prepare(
+            "SELECT signature, slot, sequence, total_supply, reserve_amount, ratio_bps, shortfall,
+                    paused
+             FROM reserve_checks WHERE NOT ?1 OR shortfall ORDER BY sequence",
+        )?;
+        let rows = statement.query_map(params![shortfalls_only], |row| {
+            Ok(ReserveCheck {
+                signature: row.get(0)?,
+                slot: get_u64(row, 1)?,
+                sequence: get_u64(row, 2)?,
+                total_supply: get_u64(row, 3)?,
+                reserve_amount: get_u64(row, 4)?,
+                ratio_bps: row.get::<_, Option<i64>>(5)?.map(|ratio| ratio as u64),
+                shortfall: row.get(6)?,
+                paused: row.get(7)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+/// Helper function to read an INTEGER column stored from a u64
+fn get_u64(row: &Row, index: usize) -> rusqlite::Result<u64> {
+    Ok(row.get::<_, i64>(index)? as u64)
+}
@@ -0,0 +1,382 @@
+//! SQLite store
+//! Every event is kept raw in `events`; summary tables hold the fields the queries need
+//! All writes are upserts keyed by (signature, event_index), so re-ingesting is idempotent
+
+use std::path::Path;
+
+use anyhow::Result;
+use dreamt_client::dreamt::events::BlacklistAction;
+use dreamt_client::events::DreamtEvent;
+use dreamt_client::quote;
+use rusqlite::{params, Connection, Transaction};
+
+use crate::transaction::{IndexedEvent, RecordedTransaction};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS events (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    sequence INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    data BLOB NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS events_sequence ON events (sequence);
+
+CREATE TABLE IF NOT EXISTS supply_changes (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    sequence INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    account TEXT NOT NULL,
+    supply_delta INTEGER NOT NULL,
+    max_supply INTEGER,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE TABLE IF NOT EXISTS purchases (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    sequence INTEGER NOT NULL,
+    user TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    item_id TEXT,
+    order_number INTEGER,
+    amount INTEGER NOT NULL,
+    payment_amount INTEGER,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS purchases_user ON purchases (user, slot);
+
+CREATE TABLE IF NOT EXISTS blacklist_changes (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    sequence INTEGER NOT NULL,
+    authority TEXT NOT NULL,
+    address TEXT NOT NULL,
+    action TEXT NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+
+CREATE TABLE IF NOT EXISTS reserve_checks (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    sequence INTEGER NOT NULL,
+    total_supply INTEGER NOT NULL,
+    reserve_amount INTEGER NOT NULL,
+    ratio_bps INTEGER,
+    shortfall INTEGER NOT NULL,
+    paused INTEGER NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+";
+
+/// Event store backed by a SQLite database
+pub struct Store {
+    pub(crate) conn: Connection,
+}
+
+impl Store {
+    /// Open (and create if needed) a database file
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Open an in-memory database
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Index every DREAMT event of a transaction in one database transaction
+    /// Returns the number of events indexed
+    pub fn ingest(&mut self, transaction: &RecordedTransaction) -> Result<usize> {
+        let events = transaction.events()?;
+        let tx = self.conn.transaction()?;
+        for event in &events {
+            upsert_event(&tx, event)?;
+        }
+        tx.commit()?;
+        Ok(events.len())
+    }
+}
+
+/// Helper function to write one event and its summary row
+fn upsert_event(tx: &Transaction, indexed: &IndexedEvent) -> Result<()> {
+    let IndexedEvent {
+        signature,
+        event_index,
+        sequence,
+        slot,
+        block_time,
+        event,
+    } = indexed;
+    let slot = *slot as i64;
+    let sequence = *sequence as i64;
+
+    tx.execute(
+        "INSERT INTO events
+             (signature, event_index, slot, block_time, sequence, name, data)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT (signature, event_index) DO UPDATE SET
+             slot = excluded.slot, block_time = excluded.block_time,
+             sequence = excluded.sequence, name = excluded.name,
+             data = excluded.data",
+        params![signature, event_index, slot, block_time, sequence, event.name(), event.data()],
+    )?;
+
+    let key = (signature.as_str(), *event_index, slot, sequence);
+    match event {
+        DreamtEvent::TokenStateInitialized(e) => upsert_supply_change(
+            tx,
+            key,
+            "initialize",
+            &e.authority.to_string(),
+            e.initial_supply as i64,
+            Some(e.max_supply),
+        ),
+        DreamtEvent::TokensMinted(e) => {
+            upsert_supply_change(tx, key, "mint", &e.user.to_string(), e.amount as i64, None)?;
+            upsert_purchase(
+                tx,
+                key,
+                &e.user.to_string(),
+                "mint",
+                None,
+                None,
+                e.amount,
+                Some(e.payment_amount),
+            )
+        }
+        DreamtEvent::TokensBurned(e) => upsert_supply_change(
+            tx,
+            key,
+            "burn",
+            &e.admin.to_string(),
+            -(e.amount as i64),
+            None,
+        ),
+        DreamtEvent::MaxSupplyUpdated(e) => upsert_supply_change(
+            tx,
+            key,
+            "max_supply",
+            &e.authority.to_string(),
+            0,
+            Some(e.new_max_supply),
+        ),
+        DreamtEvent::ItemPurchased(e) => upsert_purchase(
+            tx,
+            key,
+            &e.user.to_string(),
+            "item",
+            Some(e.item_id.as_str()),
+            None,
+            e.amount,
+            None,
+        ),
+        DreamtEvent::CartPurchased(e) => upsert_purchase(
+            tx,
+            key,
+            &e.user.to_string(),
+            "cart",
+            None,
+            Some(e.order_number),
+            e.total_amount,
+            None,
+        ),
+        DreamtEvent::MarketplaceSale(e) => upsert_purchase(
+            tx,
+            key,
+            &e.buyer.to_string(),
+            "marketplace",
+            Some(e.item_id.as_str()),
+            Some(e.order_number),
+            e.amount,
+            None,
+        ),
+        DreamtEvent::EscrowFunded(e) => upsert_purchase(
+            tx,
+            key,
+            &e.buyer.to_string(),
+            "escrow",
+            None,
+            Some(e.order_number),
+            e.amount,
+            None,
+        ),
+        DreamtEvent::PurchaseRefunded(e) => upsert_purchase(
+            tx,
+            key,
+            &e.buyer.to_string(),
+            "refund",
+            None,
+            Some(e.order_number),
+            e.amount,
+            None,
+        ),
+        DreamtEvent::BlacklistUpdated(e) => {
+            let action = match e.action {
+                BlacklistAction::Added => "added",
+                BlacklistAction::Removed => "removed",
+            };
+            tx.execute(
+                "INSERT INTO blacklist_changes
+                     (signature, event_index, slot, sequence, authority, address, action)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 ON CONFLICT (signature, event_index) DO UPDATE SET
+                     slot = excluded.slot, sequence = excluded.sequence,
+                     authority = excluded.authority, address = excluded.address,
+                     action = excluded.action",
+                params![
+                    key.0,
+                    key.1,
+                    key.2,
+                    key.3,
+                    e.authority.to_string(),
+                    e.address.to_string(),
+                    action
+                ],
+            )?;
+            Ok(())
+        }
+        DreamtEvent::ReserveVerified(e) => {
+            let ratio_bps = quote::calculate_expected_reserve(e.total_supply)
+                .ok()
+                .map(|expected| quote::calculate_reserve_ratio_bps(e.reserve_amount, expected));
+            upsert_reserve_check(
+                tx,
+                key,
+                e.total_supply,
+                e.reserve_amount,
+                ratio_bps,
+                false,
+                false,
+            )
+        }
+        DreamtEvent::ReserveShortfall(e) => upsert_reserve_check(
+            tx,
+            key,
+            e.total_supply,
+            e.reserve_amount,
+            Some(e.ratio_bps),
+            true,
+            e.paused,
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Row key: signature, event index, slot, sequence
+type RowKey<'a> = (&'a str, u32, i64, i64);
+
+fn upsert_supply_change(
+    tx: &Transaction,
+    key: RowKey,
+    kind: &str,
+    account: &str,
+    supply_delta: i64,
+    max_supply: Option<u64>,
+) -> Result<()> {
+    tx.execute(
+        "INSERT INTO supply_changes
+             (signature, event_index, slot, sequence, kind, account, supply_delta,
+              max_supply)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT (signature, event_index) DO UPDATE SET
+             slot = excluded.slot, sequence = excluded.sequence,
+             kind = excluded.kind, account = excluded.account, supply_delta = excluded.supply_delta,
+             max_supply = excluded.max_supply",
+        params![
+            key.0,
+            key.1,
+            key.2,
+            key.3,
+            kind,
+            account,
+            supply_delta,
+            max_supply.map(|max_supply| max_supply as i64)
+        ],
+    )?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn upsert_purchase(
+    tx: &Transaction,
+    key: RowKey,
+    user: &str,
+    kind: &str,
+    item_id: Option<&str>,
+    order_number: Option<u64>,
+    amount: u64,
+    payment_amount: Option<u64>,
+) -> Result<()> {
+    tx.execute(
+        "INSERT INTO purchases
+             (signature, event_index, slot, sequence, user, kind, item_id, order_number,
+              amount, payment_amount)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+         ON CONFLICT (signature, event_index) DO UPDATE SET
+             slot = excluded.slot, sequence = excluded.sequence,
+             user = excluded.user, kind = excluded.kind, item_id = excluded.item_id,
+             order_number = excluded.order_number, amount = excluded.amount,
+             payment_amount = excluded.payment_amount",
+        params![
+            key.0,
+            key.1,
+            key.2,
+            key.3,
+            user,
+            kind,
+            item_id,
+            order_number.map(|order_number| order_number as i64),
+            amount as i64,
+            payment_amount.map(|payment_amount| payment_amount as i64)
+        ],
+    )?;
+    Ok(())
+}
+
+fn upsert_reserve_check(
+    tx: &Transaction,
+    key: RowKey,
+    total_supply: u64,
+    reserve_amount: u64,
+    ratio_bps: Option<u64>,
+    shortfall: bool,
+    paused: bool,
+) -> Result<()> {
+    tx.execute(
+        "INSERT INTO reserve_checks
+             (signature, event_index, slot, sequence, total_supply, reserve_amount, ratio_bps,
+              shortfall, paused)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+         ON CONFLICT (signature, event_index) DO UPDATE SET
+             slot = excluded.slot, sequence = excluded.sequence,
+             total_supply = excluded.total_supply, reserve_amount = excluded.reserve_amount,
+             ratio_bps = excluded.ratio_bps, shortfall = excluded.shortfall,
+             paused = excluded.paused",
+        params![
+            key.0,
+            key.1,
+            key.2,
+            key.3,
+            total_supply as i64,
+            reserve_amount as i64,
+            ratio_bps.map(|ratio_bps| ratio_bps.min(i64::MAX as u64) as i64),
+            shortfall,
+            paused
+        ],
+    )?;
+    Ok(())
+}
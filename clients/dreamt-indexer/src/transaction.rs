@@ -0,0 +1,191 @@
+//! Recorded transactions
+//! Parses getTransaction responses (JSON encoding) and extracts DREAMT events
+
+use std::fs;
+use std::path::Path;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use dreamt_client::dreamt;
+use dreamt_client::events::{decode_event, decode_event_cpi, DreamtEvent};
+use serde::Deserialize;
+
+/// Transaction as returned by getTransaction with the `json` encoding
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedTransaction {
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub transaction: TransactionBody,
+    pub meta: Option<TransactionMeta>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionBody {
+    pub signatures: Vec<String>,
+    pub message: MessageBody,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageBody {
+    pub account_keys: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionMeta {
+    pub err: Option<serde_json::Value>,
+    #[serde(default)]
+    pub log_messages: Option<Vec<String>>,
+    #[serde(default)]
+    pub inner_instructions: Option<Vec<InnerInstructions>>,
+    #[serde(default)]
+    pub loaded_addresses: Option<LoadedAddresses>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InnerInstructions {
+    pub index: u8,
+    pub instructions: Vec<InnerInstruction>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InnerInstruction {
+    pub program_id_index: u8,
+    pub data: String, // base58
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct LoadedAddresses {
+    pub writable: Vec<String>,
+    pub readonly: Vec<String>,
+}
+
+/// Event extracted from a transaction, in emission order
+/// Events are ordered across transactions by their program-wide sequence number
+pub struct IndexedEvent {
+    pub signature: String,
+    pub event_index: u32,
+    pub sequence: u64,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub event: DreamtEvent,
+}
+
+impl RecordedTransaction {
+    /// Parse a getTransaction result, bare or wrapped in a JSON-RPC response
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        if let Some(result) = value.get_mut("result") {
+            value = result.take();
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Read a recorded transaction file
+    pub fn read(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    pub fn signature(&self) -> Result<&str> {
+        self.transaction
+            .signatures
+            .first()
+            .map(String::as_str)
+            .ok_or_else(|| anyhow!("transaction has no signature"))
+    }
+
+    /// DREAMT events emitted by the transaction, empty for failed transactions
+    /// Event self-CPIs are read first; program logs are the fallback for `emit!` events
+    pub fn events(&self) -> Result<Vec<IndexedEvent>> {
+        let Some(meta) = self.meta.as_ref().filter(|meta| meta.err.is_none()) else {
+            return Ok(Vec::new());
+        };
+
+        let mut events = self.cpi_events(meta)?;
+        if events.is_empty() {
+            events = log_events(meta.log_messages.as_deref().unwrap_or_default())?;
+        }
+
+        let signature = self.signature()?;
+        Ok(events
+            .into_iter()
+            .enumerate()
+            .map(|(index, event)| IndexedEvent {
+                signature: signature.to_string(),
+                event_index: index as u32,
+                sequence: event.sequence(),
+                slot: self.slot,
+                block_time: self.block_time,
+                event,
+            })
+            .collect())
+    }
+
+    /// Helper function to decode event self-CPIs from the inner instructions
+    fn cpi_events(&self, meta: &TransactionMeta) -> Result<Vec<DreamtEvent>> {
+        let loaded = meta.loaded_addresses.clone().unwrap_or_default();
+        let account_keys: Vec<&String> = self
+            .transaction
+            .message
+            .account_keys
+            .iter()
+            .chain(&loaded.writable)
+            .chain(&loaded.readonly)
+            .collect();
+
+        let mut events = Vec::new();
+        let mut inner_instructions = meta.inner_instructions.clone().unwrap_or_default();
+        inner_instructions.sort_by_key(|inner| inner.index);
+        for instruction in inner_instructions.iter().flat_map(|inner| &inner.instructions) {
+            let program_id = account_keys
+                .get(instruction.program_id_index as usize)
+                .ok_or_else(|| anyhow!("program id index out of range"))?;
+            if program_id.parse::<Pubkey>().ok() != Some(dreamt::ID) {
+                continue;
+            }
+            let data = bs58::decode(&instruction.data)
+                .into_vec()
+                .context("inner instruction data is not base58")?;
+            if let Some(event) = decode_event_cpi(&data)? {
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// Helper function to decode `Program data:` log lines written while DREAMT is executing
+fn log_events(logs: &[String]) -> Result<Vec<DreamtEvent>> {
+    let program_id = dreamt::ID.to_string();
+    let mut invocations: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for log in logs {
+        let Some(log) = log.strip_prefix("Program ") else {
+            continue;
+        };
+        if let Some(data) = log.strip_prefix("data: ") {
+            if invocations.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+            let data = STANDARD.decode(data).context("program data is not base64")?;
+            if let Some(event) = decode_event(&data)? {
+                events.push(event);
+            }
+        } else if let Some((program, rest)) = log.split_once(' ') {
+            if rest.starts_with("invoke [") {
+                invocations.push(program);
+            } else if rest == "success" || rest.starts_with("failed") {
+                invocations.pop();
+            }
+        }
+    }
+    Ok(events)
+}
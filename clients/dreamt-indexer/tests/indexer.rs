@@ -0,0 +1,198 @@
+//! Ingestion and query tests over recorded getTransaction responses in `tests/fixtures`
+
+use std::path::PathBuf;
+
+use dreamt_client::constants::{INITIAL_SUPPLY, MAX_SUPPLY, TOKEN_PRICE_USDC};
+use dreamt_client::events::DreamtEvent;
+use dreamt_indexer::queries::{PurchaseTotals, SequenceGap};
+use dreamt_indexer::store::Store;
+use dreamt_indexer::transaction::RecordedTransaction;
+
+const USER_A: &str = "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi";
+
+/// Fixtures in slot order
+const FIXTURES: &[&str] = &[
+    "initialize.json",
+    "mint_by_user.json",
+    "legacy_mint_logs.json",
+    "purchase_item.json",
+    "refund_purchase.json",
+    "blacklist_add.json",
+    "blacklist_remove.json",
+    "verify_reserve.json",
+    "admin_burn.json",
+    "failed_mint.json",
+    "reserve_shortfall.json",
+];
+
+fn fixture(name: &str) -> RecordedTransaction {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+    RecordedTransaction::read(&path).unwrap()
+}
+
+fn ingest(store: &mut Store, names: &[&str]) -> usize {
+    names.iter().map(|name| store.ingest(&fixture(name)).unwrap()).sum()
+}
+
+fn indexed_store() -> Store {
+    let mut store = Store::open_in_memory().unwrap();
+    ingest(&mut store, FIXTURES);
+    store
+}
+
+#[test]
+fn extracts_events_from_self_cpis() {
+    let events = fixture("mint_by_user.json").events().unwrap();
+    let names: Vec<_> = events.iter().map(|indexed| indexed.event.name()).collect();
+    assert_eq!(names, ["ReferralRewarded", "PointsIssued", "TokensMinted"]);
+    for (index, indexed) in events.iter().enumerate() {
+        assert_eq!(indexed.event_index, index as u32);
+        assert_eq!(indexed.sequence, 2 + index as u64);
+        assert_eq!(indexed.slot, 110);
+        assert_eq!(indexed.event.slot(), 110);
+    }
+    let DreamtEvent::TokensMinted(minted) = &events[2].event else {
+        panic!("expected TokensMinted");
+    };
+    assert_eq!(minted.user.to_string(), USER_A);
+    assert_eq!(minted.amount, 1_000);
+}
+
+#[test]
+fn falls_back_to_program_data_logs() {
+    let events = fixture("legacy_mint_logs.json").events().unwrap();
+    // The Program data line logged while Token-2022 was executing is not a DREAMT event
+    let names: Vec<_> = events.iter().map(|indexed| indexed.event.name()).collect();
+    assert_eq!(names, ["ReferralRewarded", "TokensMinted"]);
+    let sequences: Vec<_> = events.iter().map(|indexed| indexed.sequence).collect();
+    assert_eq!(sequences, [5, 6]);
+}
+
+#[test]
+fn skips_failed_transactions() {
+    assert!(fixture("failed_mint.json").events().unwrap().is_empty());
+}
+
+#[test]
+fn reingesting_is_idempotent() {
+    let mut store = indexed_store();
+    let supply = store.supply_history().unwrap();
+    let purchases = store.user_purchases(USER_A).unwrap();
+    let blacklist = store.blacklist_history(None).unwrap();
+    let reserve = store.reserve_checks(false).unwrap();
+
+    assert_eq!(ingest(&mut store, FIXTURES), 14);
+    assert_eq!(store.supply_history().unwrap(), supply);
+    assert_eq!(store.user_purchases(USER_A).unwrap(), purchases);
+    assert_eq!(store.blacklist_history(None).unwrap(), blacklist);
+    assert_eq!(store.reserve_checks(false).unwrap(), reserve);
+}
+
+#[test]
+fn supply_history_tracks_mints_and_burns() {
+    let history = indexed_store().supply_history().unwrap();
+    let changes: Vec<_> = history
+        .iter()
+        .map(|point| (point.slot, point.kind.as_str(), point.supply_delta, point.total_supply))
+        .collect();
+    let initial = INITIAL_SUPPLY as i64;
+    // USDC referral rewards leave the supply untouched
+    assert_eq!(
+        changes,
+        [
+            (100, "initialize", initial, initial),
+            (110, "mint", 1_000, initial + 1_000),
+            (120, "mint", 500, initial + 1_500),
+            (180, "burn", -300, initial + 1_200),
+        ]
+    );
+    assert!(history.iter().all(|point| point.max_supply == Some(MAX_SUPPLY)));
+    let sequences: Vec<_> = history.iter().map(|point| point.sequence).collect();
+    assert_eq!(sequences, [1, 4, 6, 13]);
+}
+
+#[test]
+fn user_purchases_and_totals() {
+    let store = indexed_store();
+    let purchases = store.user_purchases(USER_A).unwrap();
+    let kinds: Vec<_> = purchases
+        .iter()
+        .map(|purchase| (purchase.kind.as_str(), purchase.amount))
+        .collect();
+    assert_eq!(kinds, [("mint", 1_000), ("item", 200), ("refund", 50)]);
+    assert_eq!(purchases[1].item_id.as_deref(), Some("ring-01"));
+    assert_eq!(purchases[2].order_number, Some(0));
+
+    assert_eq!(
+        store.user_purchase_totals(USER_A).unwrap(),
+        PurchaseTotals {
+            purchases: 1,
+            tokens_spent: 200,
+            tokens_refunded: 50,
+            tokens_minted: 1_000,
+            usdc_paid: 1_000 * TOKEN_PRICE_USDC,
+        }
+    );
+    assert!(store.user_purchases("unknown").unwrap().is_empty());
+}
+
+#[test]
+fn blacklist_history_by_address() {
+    let store = indexed_store();
+    let history = store.blacklist_history(None).unwrap();
+    let actions: Vec<_> = history
+        .iter()
+        .map(|change| (change.slot, change.action.as_str()))
+        .collect();
+    assert_eq!(actions, [(150, "added"), (160, "removed")]);
+
+    let address = history[0].address.clone();
+    assert_eq!(store.blacklist_history(Some(&address)).unwrap(), history);
+    assert!(store.blacklist_history(Some(USER_A)).unwrap().is_empty());
+}
+
+#[test]
+fn reserve_checks_and_shortfalls() {
+    let store = indexed_store();
+    let checks = store.reserve_checks(false).unwrap();
+    assert_eq!(checks.len(), 2);
+    assert_eq!(checks[0].ratio_bps, Some(10_000));
+    assert!(!checks[0].shortfall);
+
+    let shortfalls = store.reserve_checks(true).unwrap();
+    assert_eq!(shortfalls.len(), 1);
+    assert_eq!(shortfalls[0].slot, 200);
+    assert_eq!(shortfalls[0].ratio_bps, Some(5_000));
+    assert!(shortfalls[0].paused);
+}
+
+#[test]
+fn sequence_gaps_report_missed_events() {
+    // The 700 token mint before the shortfall check was never ingested
+    let store = indexed_store();
+    assert_eq!(
+        store.sequence_gaps().unwrap(),
+        [SequenceGap {
+            first_missing: 14,
+            last_missing: 14,
+            after_slot: 180,
+            before_slot: 200,
+        }]
+    );
+
+    // Without initialize, the first event of the program is missing
+    let mut store = Store::open_in_memory().unwrap();
+    ingest(&mut store, &FIXTURES[1..8]);
+    assert_eq!(
+        store.sequence_gaps().unwrap(),
+        [SequenceGap {
+            first_missing: 1,
+            last_missing: 1,
+            after_slot: 0,
+            before_slot: 110,
+        }]
+    );
+
+    // Nothing indexed, nothing missing
+    assert!(Store::open_in_memory().unwrap().sequence_gaps().unwrap().is_empty());
+}
@@ -1,49 +1,133 @@
-/// DREAMT Token Constants
-/// Configuration for token, pricing, security, and program behavior
-
-/// Token configuration
-pub const DECIMALS: u8 = 6;
-/// Initial supply in raw token units - 8 million tokens
-pub const INITIAL_SUPPLY: u64 = 8_000_000; 
-/// Max supply in raw token units - 100 million tokens
-pub const MAX_SUPPLY: u64 = 100_000_000;
-
-/// Price configuration
-/// 0.8 USDC per token (considering 6 decimals)
-pub const TOKEN_PRICE_USDC: u64 = 8 * 10u64.pow(5); 
-/// 10 USDC minimum purchase (12.5 tokens minimum)
-pub const MIN_PURCHASE_USDC: u64 = 10 * 10u64.pow(6); 
-/// 100,000 USDC maximum purchase (as per README)
-pub const MAX_PURCHASE_USDC: u64 = 100_000 * 10u64.pow(6);
-
-/// Security configuration
-/// Maximum number of addresses in blacklist
-pub const MAX_BLACKLIST_SIZE: usize = 100;
-/// Cooldown between operations (reentrancy protection)
-pub const OPERATION_COOLDOWN: i64 = 1; 
-
-/// Timing constants
-/// 15 minutes cooldown period after pause before unpause is allowed
-pub const UNPAUSE_COOLDOWN: i64 = 15 * 60; 
-
-/// PDA seeds
-pub const TOKEN_STATE_SEED: &[u8] = b"token_state";
-pub const BLACKLIST_SEED: &[u8] = b"blacklist";
-pub const VAULT_OWNER_SEED: &[u8] = b"vault_owner";
-pub const MINT_AUTHORITY_SEED: &[u8] = b"mint_authority";
-pub const MULTISIG_SEED: &[u8] = b"multisig";
-
-/// Multisig configuration - 3 of 5 signers required
-pub const MULTISIG_THRESHOLD: u64 = 3;
-pub const MULTISIG_OWNERS: usize = 5;
-
-/// Additional security constants
-/// Number of confirmations required for operations
-pub const CONFIRMATION_THRESHOLD: u8 = 1;
-
-/// Anti-whale measures
-/// Maximum token transfer amount
-pub const MAX_TRANSFER_AMOUNT: u64 = 1_000_000 * 10u64.pow(6); // 1M tokens
-
-/// Emergency cooldown for critical operations (in seconds)
-pub const EMERGENCY_COOLDOWN: i64 = 24 * 60 * 60; // 24 hours
+/// DREAMT Token Constants
+/// Configuration for token, pricing, security, and program behavior
+
+/// Token configuration
+pub const DECIMALS: u8 = 6;
+/// Initial supply in raw token units - 8 million tokens
+pub const INITIAL_SUPPLY: u64 = 8_000_000; 
+/// Max supply in raw token units - 100 million tokens
+pub const MAX_SUPPLY: u64 = 100_000_000;
+
+/// Price configuration
+/// 0.8 USDC per token (considering 6 decimals)
+pub const TOKEN_PRICE_USDC: u64 = 8 * 10u64.pow(5); 
+/// 10 USDC minimum purchase (12.5 tokens minimum)
+pub const MIN_PURCHASE_USDC: u64 = 10 * 10u64.pow(6); 
+/// 100,000 USDC maximum purchase (as per README)
+pub const MAX_PURCHASE_USDC: u64 = 100_000 * 10u64.pow(6);
+
+/// State versioning
+/// Layout version of TokenState and Blacklist; accounts without a version are version 0
+pub const STATE_VERSION: u8 = 1;
+
+/// Security configuration
+/// Maximum number of addresses in blacklist
+pub const MAX_BLACKLIST_SIZE: usize = 100;
+/// Cooldown between operations (reentrancy protection)
+pub const OPERATION_COOLDOWN: i64 = 1; 
+
+/// Timing constants
+/// 15 minutes cooldown period after pause before unpause is allowed
+pub const UNPAUSE_COOLDOWN: i64 = 15 * 60; 
+
+/// Pause flags - each bit pauses one class of operations
+pub const PAUSE_MINT: u8 = 1 << 0;
+pub const PAUSE_PURCHASE: u8 = 1 << 1;
+pub const PAUSE_BURN: u8 = 1 << 2;
+pub const PAUSE_REDEEM: u8 = 1 << 3;
+pub const PAUSE_TRANSFER: u8 = 1 << 4;
+pub const PAUSE_ADMIN_CONFIG: u8 = 1 << 5;
+/// All pause flags
+pub const PAUSE_ALL: u8 = (1 << PAUSE_FLAG_COUNT) - 1;
+/// Number of pause flags, each with its own unpause cooldown
+pub const PAUSE_FLAG_COUNT: usize = 6;
+/// Flags set by the reserve crank on a shortfall; USDC purchases mint, so they stop too
+pub const RESERVE_SHORTFALL_PAUSE: u8 = PAUSE_MINT;
+
+/// PDA seeds
+pub const TOKEN_STATE_SEED: &[u8] = b"token_state";
+pub const BLACKLIST_SEED: &[u8] = b"blacklist";
+pub const VAULT_OWNER_SEED: &[u8] = b"vault_owner";
+pub const MINT_AUTHORITY_SEED: &[u8] = b"mint_authority";
+pub const MULTISIG_SEED: &[u8] = b"multisig";
+pub const RESERVE_HISTORY_SEED: &[u8] = b"reserve_history";
+pub const RESERVE_CONFIG_SEED: &[u8] = b"reserve_config";
+pub const TREASURY_SEED: &[u8] = b"treasury";
+pub const ORDER_CONFIG_SEED: &[u8] = b"order_config";
+pub const ORDER_SEED: &[u8] = b"order";
+pub const CATALOG_CONFIG_SEED: &[u8] = b"catalog_config";
+pub const CATALOG_ITEM_SEED: &[u8] = b"catalog_item";
+pub const ESCROW_CONFIG_SEED: &[u8] = b"escrow_config";
+pub const ESCROW_SEED: &[u8] = b"escrow";
+pub const ESCROW_VAULT_SEED: &[u8] = b"escrow_vault";
+pub const MARKETPLACE_CONFIG_SEED: &[u8] = b"marketplace_config";
+pub const MERCHANT_SEED: &[u8] = b"merchant";
+pub const COUPON_CONFIG_SEED: &[u8] = b"coupon_config";
+pub const COUPON_SEED: &[u8] = b"coupon";
+pub const LOYALTY_CONFIG_SEED: &[u8] = b"loyalty_config";
+pub const POINTS_MINT_SEED: &[u8] = b"points_mint";
+pub const POINTS_CREDIT_SEED: &[u8] = b"points_credit";
+pub const STAKE_CONFIG_SEED: &[u8] = b"stake_config";
+pub const STAKE_SEED: &[u8] = b"stake";
+pub const STAKE_VAULT_SEED: &[u8] = b"stake_vault";
+pub const REFERRAL_CONFIG_SEED: &[u8] = b"referral_config";
+pub const REFERRAL_SEED: &[u8] = b"referral";
+pub const INTENT_NONCE_SEED: &[u8] = b"intent_nonce";
+pub const MINT_DELEGATE_SEED: &[u8] = b"mint_delegate";
+pub const GUARDIAN_SET_SEED: &[u8] = b"guardian_set";
+/// Event authority seed fixed by Anchor's event-cpi dispatcher
+pub const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+pub const BUYER_ORDERS_SEED: &[u8] = b"buyer_orders";
+
+/// Multisig configuration - 3 of 5 signers required
+pub const MULTISIG_THRESHOLD: u64 = 3;
+pub const MULTISIG_OWNERS: usize = 5;
+/// Maximum number of guardian keys allowed to fast-pause
+pub const MAX_GUARDIANS: usize = 10;
+
+/// Purchase configuration
+/// Maximum length of an item ID in bytes
+pub const MAX_ITEM_ID_LEN: usize = 32;
+/// Maximum number of lines in a purchase_items cart
+pub const MAX_CART_LINES: usize = 10;
+/// Item ID recorded on orders created for multi-item carts
+pub const CART_ORDER_ITEM_ID: &str = "cart";
+
+/// Additional security constants
+/// Number of confirmations required for operations
+pub const CONFIRMATION_THRESHOLD: u8 = 1;
+
+/// Anti-whale measures
+/// Maximum token transfer amount
+pub const MAX_TRANSFER_AMOUNT: u64 = 1_000_000 * 10u64.pow(6); // 1M tokens
+
+/// Emergency cooldown for critical operations (in seconds)
+pub const EMERGENCY_COOLDOWN: i64 = 24 * 60 * 60; // 24 hours
+
+/// Proof-of-reserve history configuration
+/// Number of reserve checks kept in the on-chain ring buffer
+pub const RESERVE_HISTORY_SIZE: usize = 64;
+/// Maximum entries returned by get_reserve_history (return data is capped at 1024 bytes)
+pub const MAX_RESERVE_HISTORY_RETURN: usize = 14;
+/// Basis point denominator used for reserve ratios and fees (10_000 = 100%)
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Marketplace configuration
+/// Maximum platform fee taken from merchant sales (20%)
+pub const MAX_PLATFORM_FEE_BPS: u64 = 2_000;
+
+/// Staking configuration
+/// Number of staking tiers, ordered by ascending minimum stake
+pub const STAKE_TIER_COUNT: usize = 3;
+/// Maximum purchase discount granted by a staking tier (50%)
+pub const MAX_STAKE_DISCOUNT_BPS: u64 = 5_000;
+/// Maximum mint_by_user limit multiplier granted by a staking tier (5x)
+pub const MAX_STAKE_MINT_LIMIT_BPS: u64 = 50_000;
+
+/// Referral configuration
+/// Maximum referrer reward share of a user mint (20%)
+pub const MAX_REFERRAL_REWARD_BPS: u64 = 2_000;
+
+/// Gasless purchase intents
+/// Domain separator prefixed to every signed purchase intent message
+pub const PURCHASE_INTENT_DOMAIN: &[u8] = b"dreamt:purchase_intent:v2";
@@ -1,130 +1,265 @@
-use anchor_lang::prelude::*;
-
-#[error_code]
-pub enum DiamondTokenError {
-    #[msg("Invalid multisig threshold")]
-    InvalidMultisigThreshold,
-
-    #[msg("Invalid token state")]
-    InvalidTokenState,
-
-    #[msg("Invalid blacklist PDA")]
-    InvalidBlacklist,
-
-    #[msg("Address is blacklisted")]
-    AddressBlacklisted,
-
-    #[msg("Invalid payment token - only USDC accepted")]
-    InvalidPaymentToken,
-
-    #[msg("Invalid token owner")]
-    InvalidOwner,
-
-    #[msg("Address is already blacklisted")]
-    AddressAlreadyBlacklisted,
-
-    #[msg("Address is not blacklisted")]
-    AddressNotBlacklisted,
-
-    #[msg("Blacklist is full")]
-    BlacklistFull,
-
-    #[msg("Insufficient balance")]
-    InsufficientBalance,
-
-    #[msg("Insufficient reserve")]
-    InsufficientReserve,
-
-    #[msg("Invalid amount")]
-    InvalidAmount,
-
-    #[msg("Invalid token account")]
-    InvalidTokenAccount,
-
-    #[msg("Purchase amount is too small")]
-    PurchaseAmountTooSmall,
-
-    #[msg("Purchase amount is too large")]
-    PurchaseAmountTooLarge,
-
-    #[msg("Max supply would be exceeded")]
-    MaxSupplyExceeded,
-
-    #[msg("Cannot increase max supply")]
-    CannotIncreaseMaxSupply,
-
-    #[msg("Invalid max supply")]
-    InvalidMaxSupply,
-
-    #[msg("Max supply reduction too large")]
-    MaxSupplyReductionTooLarge,
-
-    #[msg("Token operations are paused")]
-    ProgramPaused,
-
-    #[msg("Invalid token decimals")]
-    InvalidDecimals,
-
-    #[msg("Math operation overflow")]
-    MathOverflow,
-
-    #[msg("Token is already paused")]
-    AlreadyPaused,
-
-    #[msg("Token is not paused")]
-    NotPaused,
-
-    #[msg("Not authorized")]
-    NotAuthorized,
-
-    #[msg("Insufficient funds")]
-    InsufficientFunds,
-
-    #[msg("Invalid vault owner")]
-    InvalidVaultOwner,
-
-    #[msg("Invalid authority")]
-    InvalidAuthority,
-
-    #[msg("Invalid multisig")]
-    InvalidMultisig,
-
-    #[msg("Multisig verification failed")]
-    MultisigVerificationFailed,
-
-    #[msg("Source address is blacklisted")]
-    SourceAddressBlacklisted,
-
-    #[msg("Destination address is blacklisted")]
-    DestinationAddressBlacklisted,
-
-    #[msg("Unpause cooldown not elapsed")]
-    UnpauseCooldownNotElapsed,
-
-    #[msg("Invalid token version - SPL Token 2022 required")]
-    InvalidTokenVersion,
-
-    #[msg("Operation in progress - reentrancy not allowed")]
-    ReentrancyNotAllowed,
-
-    #[msg("Operation cooldown not elapsed")]
-    OperationCooldownNotElapsed,
-
-    #[msg("Invalid multisig transaction")]
-    InvalidMultisigTransaction,
-
-    #[msg("Invalid timestamp")]
-    InvalidTimestamp,
-
-    #[msg("Missing multisig signer")]
-    MissingMultisigSigner,
-
-    #[msg("Multisig already initialized")]
-    MultisigAlreadyInitialized,
-
-    #[msg("Transfer hook error")]
-    TransferHookError,
-
-    #[msg("Invalid token program provided")]
-    InvalidTokenProgram,
-}
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum DiamondTokenError {
+    #[msg("Invalid multisig threshold")]
+    InvalidMultisigThreshold,
+
+    #[msg("Invalid token state")]
+    InvalidTokenState,
+
+    #[msg("Invalid blacklist PDA")]
+    InvalidBlacklist,
+
+    #[msg("Address is blacklisted")]
+    AddressBlacklisted,
+
+    #[msg("Invalid payment token - only USDC accepted")]
+    InvalidPaymentToken,
+
+    #[msg("Invalid token owner")]
+    InvalidOwner,
+
+    #[msg("Address is already blacklisted")]
+    AddressAlreadyBlacklisted,
+
+    #[msg("Address is not blacklisted")]
+    AddressNotBlacklisted,
+
+    #[msg("Blacklist is full")]
+    BlacklistFull,
+
+    #[msg("Insufficient balance")]
+    InsufficientBalance,
+
+    #[msg("Insufficient reserve")]
+    InsufficientReserve,
+
+    #[msg("Invalid amount")]
+    InvalidAmount,
+
+    #[msg("Invalid token account")]
+    InvalidTokenAccount,
+
+    #[msg("Purchase amount is too small")]
+    PurchaseAmountTooSmall,
+
+    #[msg("Purchase amount is too large")]
+    PurchaseAmountTooLarge,
+
+    #[msg("Max supply would be exceeded")]
+    MaxSupplyExceeded,
+
+    #[msg("Cannot increase max supply")]
+    CannotIncreaseMaxSupply,
+
+    #[msg("Invalid max supply")]
+    InvalidMaxSupply,
+
+    #[msg("Max supply reduction too large")]
+    MaxSupplyReductionTooLarge,
+
+    #[msg("Token operations are paused")]
+    ProgramPaused,
+
+    #[msg("Invalid token decimals")]
+    InvalidDecimals,
+
+    #[msg("Math operation overflow")]
+    MathOverflow,
+
+    #[msg("Token is already paused")]
+    AlreadyPaused,
+
+    #[msg("Token is not paused")]
+    NotPaused,
+
+    #[msg("Not authorized")]
+    NotAuthorized,
+
+    #[msg("Insufficient funds")]
+    InsufficientFunds,
+
+    #[msg("Invalid vault owner")]
+    InvalidVaultOwner,
+
+    #[msg("Invalid authority")]
+    InvalidAuthority,
+
+    #[msg("Invalid multisig")]
+    InvalidMultisig,
+
+    #[msg("Multisig verification failed")]
+    MultisigVerificationFailed,
+
+    #[msg("Source address is blacklisted")]
+    SourceAddressBlacklisted,
+
+    #[msg("Destination address is blacklisted")]
+    DestinationAddressBlacklisted,
+
+    #[msg("Unpause cooldown not elapsed")]
+    UnpauseCooldownNotElapsed,
+
+    #[msg("Invalid token version - SPL Token 2022 required")]
+    InvalidTokenVersion,
+
+    #[msg("Operation in progress - reentrancy not allowed")]
+    ReentrancyNotAllowed,
+
+    #[msg("Operation cooldown not elapsed")]
+    OperationCooldownNotElapsed,
+
+    #[msg("Invalid multisig transaction")]
+    InvalidMultisigTransaction,
+
+    #[msg("Invalid timestamp")]
+    InvalidTimestamp,
+
+    #[msg("Missing multisig signer")]
+    MissingMultisigSigner,
+
+    #[msg("Multisig already initialized")]
+    MultisigAlreadyInitialized,
+
+    #[msg("Transfer hook error")]
+    TransferHookError,
+
+    #[msg("Invalid token program provided")]
+    InvalidTokenProgram,
+
+    #[msg("Invalid reserve ratio")]
+    InvalidReserveRatio,
+
+    #[msg("Invalid reserve vault")]
+    InvalidReserveVault,
+
+    #[msg("Invalid treasury destination")]
+    InvalidTreasuryDestination,
+
+    #[msg("Invalid order status transition")]
+    InvalidOrderStatusTransition,
+
+    #[msg("Invalid item price")]
+    InvalidPrice,
+
+    #[msg("Item is not active")]
+    ItemInactive,
+
+    #[msg("Item is out of stock")]
+    OutOfStock,
+
+    #[msg("Purchase amount does not match catalog price")]
+    PurchaseAmountMismatch,
+
+    #[msg("Invalid escrow status for this operation")]
+    InvalidEscrowStatus,
+
+    #[msg("Escrow release timeout not elapsed")]
+    EscrowTimeoutNotElapsed,
+
+    #[msg("Escrow release timeout already elapsed")]
+    EscrowTimeoutElapsed,
+
+    #[msg("Refund amount exceeds escrowed amount")]
+    InvalidRefundAmount,
+
+    #[msg("Refund exceeds the amount paid for the order")]
+    RefundExceedsPayment,
+
+    #[msg("Order funds are not held in the vault")]
+    OrderNotVaultSettled,
+
+    #[msg("Invalid cart")]
+    InvalidCart,
+
+    #[msg("Catalog item does not match cart line")]
+    CatalogItemMismatch,
+
+    #[msg("Invalid platform fee")]
+    InvalidPlatformFee,
+
+    #[msg("Merchant is suspended")]
+    MerchantSuspended,
+
+    #[msg("Invalid coupon")]
+    InvalidCoupon,
+
+    #[msg("Coupon code does not match")]
+    CouponCodeMismatch,
+
+    #[msg("Coupon has expired")]
+    CouponExpired,
+
+    #[msg("Coupon has no uses left")]
+    CouponExhausted,
+
+    #[msg("Coupon is not valid for this item")]
+    CouponNotApplicable,
+
+    #[msg("Invalid loyalty accounts")]
+    InvalidLoyaltyAccounts,
+
+    #[msg("Invalid loyalty rate")]
+    InvalidLoyaltyRate,
+
+    #[msg("Invalid staking tiers")]
+    InvalidStakeTiers,
+
+    #[msg("Invalid stake accounts")]
+    InvalidStakeAccounts,
+
+    #[msg("Insufficient staked balance")]
+    InsufficientStake,
+
+    #[msg("Unbonding period not elapsed")]
+    UnbondingNotElapsed,
+
+    #[msg("Invalid referral accounts")]
+    InvalidReferral,
+
+    #[msg("Users cannot refer themselves")]
+    SelfReferral,
+
+    #[msg("Invalid referral reward")]
+    InvalidReferralReward,
+
+    #[msg("Slippage limit exceeded")]
+    SlippageExceeded,
+
+    #[msg("Invalid purchase intent signature")]
+    InvalidIntentSignature,
+
+    #[msg("Purchase intent has expired")]
+    IntentExpired,
+
+    #[msg("Invalid purchase intent nonce")]
+    InvalidIntentNonce,
+
+    #[msg("Invalid pause flags")]
+    InvalidPauseFlags,
+
+    #[msg("Invalid guardian set")]
+    InvalidGuardians,
+
+    #[msg("State account must be migrated first")]
+    StateNotMigrated,
+
+    #[msg("State accounts are already migrated")]
+    StateAlreadyMigrated,
+
+    #[msg("Invalid state account")]
+    InvalidStateAccount,
+
+    #[msg("Catalog item has no merchant")]
+    ItemHasNoMerchant,
+
+    #[msg("Merchant items must be bought through the marketplace")]
+    MerchantItemNotAllowed,
+
+    #[msg("Not enough multisig signers")]
+    InsufficientMultisigSigners,
+
+    #[msg("Pause reason codes are only recorded for guardian pauses")]
+    PauseReasonNotRecorded,
+}
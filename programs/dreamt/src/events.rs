@@ -1,79 +1,536 @@
-use anchor_lang::prelude::*;
-
-#[event]
-pub struct TokenStateInitialized {
-    pub authority: Pubkey,
-    pub mint: Pubkey,
-    pub initial_supply: u64,
-    pub max_supply: u64,
-    pub multisig: Pubkey,
-}
-
-#[event]
-pub struct TokensMinted {
-    pub user: Pubkey,
-    pub amount: u64,
-    pub payment_amount: u64,
-    pub payment_token: Option<Pubkey>,
-}
-
-#[event]
-pub struct TokensBurned {
-    pub admin: Pubkey,
-    pub amount: u64,
-    pub refund_amount: u64,
-    pub refund_token: Pubkey,
-}
-
-#[event]
-pub struct ProgramPaused {
-    pub authority: Pubkey,
-    pub timestamp: i64,
-}
-
-#[event]
-pub struct ProgramUnpaused {
-    pub authority: Pubkey,
-    pub timestamp: i64,
-}
-
-#[event]
-pub struct MaxSupplyUpdated {
-    pub authority: Pubkey,
-    pub old_max_supply: u64,
-    pub new_max_supply: u64,
-}
-
-#[event]
-pub struct BlacklistUpdated {
-    pub authority: Pubkey,
-    pub address: Pubkey,
-    pub action: BlacklistAction,
-}
-
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub enum BlacklistAction {
-    Added,
-    Removed,
-}
-
-#[event]
-pub struct ItemPurchased {
-    pub user: Pubkey,
-    pub amount: u64,
-    pub item_id: String,
-}
-
-#[event]
-pub struct ReserveVerified {
-    pub total_supply: u64,
-    pub reserve_amount: u64,
-    pub reserve_token: Pubkey,
-}
-
-#[event]
-pub struct TransferHookExecuted {
-    pub source: Pubkey,
-    pub destination: Pubkey,
-    pub amount: u64,
-}
+use anchor_lang::prelude::*;
+use crate::state::{
+    DiscountKind, EscrowStatus, OrderStatus, PriceCurrency, StakeTier,
+};
+
+/// Emit an event through Anchor's event-cpi self-CPI
+/// Stamps `sequence` from the token state counter and fills in `slot` from the clock;
+/// events built ahead of time by helpers leave `sequence` at zero to be stamped here
+macro_rules! emit_event {
+    (@sequenced $ctx:expr, $event:expr) => {{
+        let event = $event;
+        let ctx = &$ctx;
+        emit_cpi!(event);
+    }};
+    ($ctx:expr, $event:ident { $($fields:tt)* }) => {{
+        let event = $event { sequence: 0, slot: Clock::get()?.slot, $($fields)* };
+        emit_event!($ctx, event);
+    }};
+    ($ctx:expr, $event:expr) => {{
+        let mut event = $event;
+        event.sequence = $ctx.accounts.token_state.next_event_sequence()?;
+        emit_event!(@sequenced $ctx, event);
+    }};
+}
+
+#[event]
+pub struct TokenStateInitialized {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    pub initial_supply: u64,
+    pub max_supply: u64,
+    pub multisig: Pubkey,
+}
+
+#[event]
+pub struct TokensMinted {
+    pub sequence: u64,
+    pub slot: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub payment_amount: u64,
+    pub payment_token: Option<Pubkey>,
+}
+
+#[event]
+pub struct TokensBurned {
+    pub sequence: u64,
+    pub slot: u64,
+    pub admin: Pubkey,
+    pub amount: u64,
+    pub refund_amount: u64,
+    pub refund_token: Pubkey,
+    pub recipient: Pubkey,
+}
+
+#[event]
+pub struct ProgramPaused {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub flags: u8,
+    pub paused_flags: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProgramUnpaused {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub flags: u8,
+    pub paused_flags: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MaxSupplyUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub old_max_supply: u64,
+    pub new_max_supply: u64,
+}
+
+#[event]
+pub struct BlacklistUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub address: Pubkey,
+    pub action: BlacklistAction,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum BlacklistAction {
+    Added,
+    Removed,
+}
+
+#[event]
+pub struct ItemPurchased {
+    pub sequence: u64,
+    pub slot: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub item_id: String,
+}
+
+#[event]
+pub struct OrderCreated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub order_number: u64,
+    pub buyer: Pubkey,
+    pub item_id: String,
+    pub amount: u64,
+    pub shipping_info_hash: [u8; 32],
+}
+
+#[event]
+pub struct OrderStatusUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub order_number: u64,
+    pub buyer: Pubkey,
+    pub merchant: Pubkey,
+    pub old_status: OrderStatus,
+    pub new_status: OrderStatus,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PurchaseRefunded {
+    pub sequence: u64,
+    pub slot: u64,
+    pub order_number: u64,
+    pub buyer: Pubkey,
+    pub amount: u64,
+    pub total_refunded: u64,
+    pub refunded_by: Pubkey,
+}
+
+#[event]
+pub struct MerchantUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub old_merchant: Pubkey,
+    pub new_merchant: Pubkey,
+}
+
+#[event]
+pub struct CatalogManagerUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub old_manager: Pubkey,
+    pub new_manager: Pubkey,
+}
+
+#[event]
+pub struct CatalogItemUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub manager: Pubkey,
+    pub item_id: String,
+    pub price: u64,
+    pub price_currency: PriceCurrency,
+    pub stock: u64,
+    pub is_active: bool,
+    pub merchant: Pubkey,
+    pub action: CatalogAction,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CatalogAction {
+    Created,
+    Updated,
+    Removed,
+}
+
+#[event]
+pub struct EscrowConfigUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub arbitrator: Pubkey,
+    pub release_timeout: i64,
+}
+
+#[event]
+pub struct EscrowFunded {
+    pub sequence: u64,
+    pub slot: u64,
+    pub order_number: u64,
+    pub buyer: Pubkey,
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub release_after: i64,
+}
+
+#[event]
+pub struct EscrowDisputed {
+    pub sequence: u64,
+    pub slot: u64,
+    pub order_number: u64,
+    pub buyer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EscrowSettled {
+    pub sequence: u64,
+    pub slot: u64,
+    pub order_number: u64,
+    pub merchant_amount: u64,
+    pub buyer_refund: u64,
+    pub settled_by: Pubkey,
+    pub status: EscrowStatus,
+}
+
+#[event]
+pub struct CartLinePurchased {
+    pub sequence: u64,
+    pub slot: u64,
+    pub order_number: u64,
+    pub line_index: u8,
+    pub item_id: String,
+    pub quantity: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct CartPurchased {
+    pub sequence: u64,
+    pub slot: u64,
+    pub order_number: u64,
+    pub user: Pubkey,
+    pub line_count: u8,
+    pub total_amount: u64,
+}
+
+#[event]
+pub struct MarketplaceConfigUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub platform_fee_bps: u64,
+}
+
+#[event]
+pub struct MerchantRegistered {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub merchant: Pubkey,
+    pub receiving_account: Pubkey,
+}
+
+#[event]
+pub struct MerchantStatusUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub merchant: Pubkey,
+    pub is_active: bool,
+}
+
+#[event]
+pub struct MarketplaceSale {
+    pub sequence: u64,
+    pub slot: u64,
+    pub order_number: u64,
+    pub merchant: Pubkey,
+    pub buyer: Pubkey,
+    pub item_id: String,
+    pub amount: u64,
+    pub platform_fee: u64,
+    pub merchant_amount: u64,
+}
+
+#[event]
+pub struct MarketingRoleUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub old_marketing: Pubkey,
+    pub new_marketing: Pubkey,
+}
+
+#[event]
+pub struct CouponCreated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub marketing: Pubkey,
+    pub code_hash: [u8; 32],
+    pub kind: DiscountKind,
+    pub value: u64,
+    pub max_uses: u32,
+    pub expires_at: i64,
+    pub item_id: Option<String>,
+    pub redeemer: Pubkey,
+}
+
+#[event]
+pub struct CouponRedeemed {
+    pub sequence: u64,
+    pub slot: u64,
+    pub code_hash: [u8; 32],
+    pub user: Pubkey,
+    pub item_id: String,
+    pub original_price: u64,
+    pub discount: u64,
+    pub uses: u32,
+}
+
+#[event]
+pub struct LoyaltyRatesUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub points_mint: Pubkey,
+    pub purchase_rate_bps: u64,
+    pub mint_rate_bps: u64,
+    pub points_per_credit: u64,
+}
+
+#[event]
+pub struct PointsIssued {
+    pub sequence: u64,
+    pub slot: u64,
+    pub user: Pubkey,
+    pub points: u64,
+    pub source: PointsSource,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PointsSource {
+    Purchase,
+    Mint,
+}
+
+#[event]
+pub struct PointsRedeemed {
+    pub sequence: u64,
+    pub slot: u64,
+    pub user: Pubkey,
+    pub points_burned: u64,
+    pub debt_paid: u64,
+    pub credit: u64,
+    pub credit_balance: u64,
+}
+
+#[event]
+pub struct PointsCreditUsed {
+    pub sequence: u64,
+    pub slot: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub credit_balance: u64,
+}
+
+#[event]
+pub struct StakingConfigured {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub unbonding_period: i64,
+    pub tiers: Vec<StakeTier>,
+}
+
+#[event]
+pub struct Staked {
+    pub sequence: u64,
+    pub slot: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub staked_amount: u64,
+}
+
+#[event]
+pub struct UnstakeRequested {
+    pub sequence: u64,
+    pub slot: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub unbonding_amount: u64,
+    pub unbonding_ends_at: i64,
+}
+
+#[event]
+pub struct StakeWithdrawn {
+    pub sequence: u64,
+    pub slot: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct StakeDiscountApplied {
+    pub sequence: u64,
+    pub slot: u64,
+    pub user: Pubkey,
+    pub tier: u8,
+    pub discount: u64,
+}
+
+#[event]
+pub struct ReferralConfigUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub reward_bps: u64,
+}
+
+#[event]
+pub struct ReferrerRegistered {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub referrer: Pubkey,
+}
+
+#[event]
+pub struct ReferralRewarded {
+    pub sequence: u64,
+    pub slot: u64,
+    pub referrer: Pubkey,
+    pub user: Pubkey,
+    pub payment_amount: u64,
+    pub reward: u64,
+}
+
+#[event]
+pub struct PurchaseIntentExecuted {
+    pub sequence: u64,
+    pub slot: u64,
+    pub user: Pubkey,
+    pub relayer: Pubkey,
+    pub nonce: u64,
+    pub amount: u64,
+    pub payment_amount: u64,
+}
+
+#[event]
+pub struct GuardiansUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub guardians: Vec<Pubkey>,
+}
+
+#[event]
+pub struct GuardianPaused {
+    pub sequence: u64,
+    pub slot: u64,
+    pub guardian: Pubkey,
+    pub flags: u8,
+    pub reason_code: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StateMigrated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub account: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+}
+
+#[event]
+pub struct PointsRevoked {
+    pub sequence: u64,
+    pub slot: u64,
+    pub user: Pubkey,
+    pub order_number: u64,
+    pub points: u64,
+    pub credit_revoked: u64,
+    pub points_debt: u64,
+}
+
+#[event]
+pub struct ReserveVerified {
+    pub sequence: u64,
+    pub slot: u64,
+    pub total_supply: u64,
+    pub reserve_amount: u64,
+    pub reserve_token: Pubkey,
+}
+
+#[event]
+pub struct ReserveShortfall {
+    pub sequence: u64,
+    pub slot: u64,
+    pub total_supply: u64,
+    pub reserve_amount: u64,
+    pub expected_reserve: u64,
+    pub ratio_bps: u64,
+    pub min_reserve_ratio_bps: u64,
+    pub reserve_token: Pubkey,
+    pub paused: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReserveConfigUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub reserve_vault: Pubkey,
+    pub min_reserve_ratio_bps: u64,
+}
+
+#[event]
+pub struct TreasuryDestinationUpdated {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub old_destination: Pubkey,
+    pub new_destination: Pubkey,
+}
+
+#[event]
+pub struct TreasuryWithdrawn {
+    pub sequence: u64,
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub remaining_reserve: u64,
+    pub ratio_bps: u64,
+}
@@ -0,0 +1,102 @@
+//! DREAMT Pricing Math
+//! Pure pricing, refund, reserve and staking calculations shared with off-chain quotes
+
+use anchor_lang::prelude::*;
+use crate::{
+    constants::{BPS_DENOMINATOR, MAX_PURCHASE_USDC, MIN_PURCHASE_USDC, TOKEN_PRICE_USDC},
+    error::DiamondTokenError,
+    state::{StakeTier, TokenState},
+};
+
+/// Price a user mint and check purchase limits and max supply
+/// Returns the USDC payment amount and the total supply after minting
+#[inline(always)]
+pub fn calculate_mint_purchase(
+    token_state: &TokenState,
+    amount: u64,
+    max_purchase_usdc: u64,
+) -> Result<(u64, u64)> {
+    // Calculate payment amount (0.8 USDC per token)
+    let payment_amount = amount
+        .checked_mul(TOKEN_PRICE_USDC)
+        .ok_or(DiamondTokenError::MathOverflow)?;
+
+    // Check minimum and maximum purchase amount
+    require!(
+        payment_amount >= MIN_PURCHASE_USDC,
+        DiamondTokenError::PurchaseAmountTooSmall
+    );
+    require!(
+        payment_amount <= max_purchase_usdc,
+        DiamondTokenError::PurchaseAmountTooLarge
+    );
+
+    // Check if minting would exceed max supply
+    let new_supply = token_state
+        .total_supply
+        .checked_add(amount)
+        .ok_or(DiamondTokenError::MathOverflow)?;
+    require!(
+        new_supply <= token_state.max_supply,
+        DiamondTokenError::MaxSupplyExceeded
+    );
+
+    Ok((payment_amount, new_supply))
+}
+
+/// Price an exact USDC input
+/// Rounds down so the buyer is never charged for a partial token
+#[inline(always)]
+pub fn calculate_tokens_for_payment(payment_amount: u64) -> u64 {
+    payment_amount / TOKEN_PRICE_USDC
+}
+
+/// Calculate the admin_burn USDC refund for `amount` tokens
+#[inline(always)]
+pub fn calculate_refund_amount(amount: u64) -> Result<u64> {
+    amount
+        .checked_mul(TOKEN_PRICE_USDC)
+        .ok_or(error!(DiamondTokenError::MathOverflow))
+}
+
+/// Calculate the USDC reserve required to back a supply
+#[inline(always)]
+pub fn calculate_expected_reserve(total_supply: u64) -> Result<u64> {
+    total_supply
+        .checked_mul(TOKEN_PRICE_USDC)
+        .ok_or(error!(DiamondTokenError::MathOverflow))
+}
+
+/// Calculate the reserve ratio in basis points
+/// Uses u128 intermediates and saturates instead of overflowing for tiny expected reserves
+#[inline(always)]
+pub fn calculate_reserve_ratio_bps(reserve_amount: u64, expected_reserve: u64) -> u64 {
+    if expected_reserve == 0 {
+        return u64::MAX;
+    }
+    let ratio = (reserve_amount as u128) * (BPS_DENOMINATOR as u128) / (expected_reserve as u128);
+    u64::try_from(ratio).unwrap_or(u64::MAX)
+}
+
+/// Calculate the mint_by_user purchase limit for a staking tier
+#[inline(always)]
+pub fn stake_mint_limit(tier: StakeTier) -> Result<u64> {
+    let limit = (MAX_PURCHASE_USDC as u128)
+        .checked_mul(tier.mint_limit_bps as u128)
+        .ok_or(DiamondTokenError::MathOverflow)?
+        / (BPS_DENOMINATOR as u128);
+    u64::try_from(limit).map_err(|_| error!(DiamondTokenError::MathOverflow))
+}
+
+/// Calculate a staking tier discount on a purchase price
+/// Always leaves at least one raw unit to pay
+#[inline(always)]
+pub fn calculate_stake_discount(tier: StakeTier, price: u64) -> Result<u64> {
+    let discount = (price as u128)
+        .checked_mul(tier.discount_bps as u128)
+        .ok_or(DiamondTokenError::MathOverflow)?
+        / (BPS_DENOMINATOR as u128);
+    Ok(u64::try_from(discount)
+        .map_err(|_| error!(DiamondTokenError::MathOverflow))?
+        .min(price.saturating_sub(1)))
+}
@@ -1,153 +1,1317 @@
-use anchor_lang::prelude::*;
-use crate::{error::DiamondTokenError, constants::OPERATION_COOLDOWN};
-
-/// Token state account storing program configuration and state
-/// Optimized storage layout with robust security features
-#[account]
-#[derive(Debug)]
-pub struct TokenState {
-    pub authority: Pubkey,         // 32 bytes
-    pub mint: Pubkey,              // 32 bytes
-    pub total_supply: u64,         // 8 bytes
-    pub max_supply: u64,           // 8 bytes
-    pub is_paused: bool,           // 1 byte
-    pub last_pause_timestamp: i64, // 8 bytes
-    pub multisig: Pubkey,          // 32 bytes
-    pub vault_owner: Pubkey,       // 32 bytes - PDA that owns the vault
-    pub bump: u8,                  // 1 byte
-    pub in_operation: bool,        // 1 byte - reentrancy guard
-    pub last_operation_timestamp: i64, // 8 bytes - operation cooldown
-}
-
-impl Default for TokenState {
-    fn default() -> Self {
-        Self {
-            authority: Pubkey::default(),
-            mint: Pubkey::default(),
-            total_supply: 0,
-            max_supply: 0,
-            is_paused: false,
-            last_pause_timestamp: 0,
-            multisig: Pubkey::default(),
-            vault_owner: Pubkey::default(),
-            bump: 0,
-            in_operation: false,
-            last_operation_timestamp: 0,
-        }
-    }
-}
-
-impl TokenState {
-    pub const LEN: usize = 163; // 8 + 32 + 32 + 8 + 8 + 1 + 8 + 32 + 32 + 1 + 1 + 8
-
-    /// Start an operation with reentrancy protection
-    /// Enhanced timing attack protection and secure cooldown checks
-    #[inline(always)]
-    pub fn start_operation(&mut self) -> Result<()> {
-        // Check if already in an operation
-        require!(!self.in_operation, DiamondTokenError::ReentrancyNotAllowed);
-        
-        // Get current timestamp with error handling
-        let current_time = Clock::get()?.unix_timestamp;
-        
-        // Perform checked subtraction for cooldown calculation
-        let time_diff = current_time
-            .checked_sub(self.last_operation_timestamp)
-            .ok_or(DiamondTokenError::MathOverflow)?;
-            
-        // Verify cooldown has passed
-        require!(
-            time_diff >= OPERATION_COOLDOWN,
-            DiamondTokenError::OperationCooldownNotElapsed
-        );
-
-        // Set reentrancy guard and update timestamp
-        self.in_operation = true;
-        self.last_operation_timestamp = current_time;
-        
-        Ok(())
-    }
-
-    /// End an operation and clear reentrancy guard
-    #[inline(always)]
-    pub fn end_operation(&mut self) {
-        self.in_operation = false;
-    }
-
-    /// Check if the provided address is an admin
-    #[inline(always)]
-    pub fn is_admin(&self, admin: &Pubkey) -> bool {
-        self.authority == *admin
-    }
-    
-    /// Update total supply with checked math to prevent overflows
-    #[inline(always)]
-    pub fn update_total_supply_add(&mut self, amount: u64) -> Result<()> {
-        self.total_supply = self.total_supply
-            .checked_add(amount)
-            .ok_or(DiamondTokenError::MathOverflow)?;
-        Ok(())
-    }
-    
-    /// Subtract from total supply with checked math to prevent underflows
-    #[inline(always)]
-    pub fn update_total_supply_sub(&mut self, amount: u64) -> Result<()> {
-        self.total_supply = self.total_supply
-            .checked_sub(amount)
-            .ok_or(DiamondTokenError::MathOverflow)?;
-        Ok(())
-    }
-}
-
-/// Blacklist account storing addresses that are not allowed to interact with the token
-/// Optimized for faster lookup and memory efficiency
-#[account]
-#[derive(Default, Debug)]
-pub struct Blacklist {
-    pub addresses: Vec<Pubkey>, // up to MAX_BLACKLIST_SIZE
-    pub bump: u8,
-}
-
-impl Blacklist {
-    pub fn space() -> usize {
-        8 + 4 + (32 * crate::constants::MAX_BLACKLIST_SIZE) + 1  // More explicit calculation
-    }
-    
-    /// Optimized contains check for better gas efficiency
-    #[inline(always)]
-    pub fn contains(&self, address: &Pubkey) -> bool {
-        self.addresses.contains(address)
-    }
-    
-    /// Safe add method with blacklist size validation
-    #[inline(always)]
-    pub fn add(&mut self, address: Pubkey) -> Result<()> {
-        // Check if address is already blacklisted
-        require!(
-            !self.addresses.contains(&address),
-            DiamondTokenError::AddressAlreadyBlacklisted
-        );
-        
-        // Check if blacklist is full
-        require!(
-            self.addresses.len() < crate::constants::MAX_BLACKLIST_SIZE,
-            DiamondTokenError::BlacklistFull
-        );
-        
-        // Add address to blacklist
-        self.addresses.push(address);
-        Ok(())
-    }
-    
-    /// Safe remove method with existence validation
-    #[inline(always)]
-    pub fn remove(&mut self, address: &Pubkey) -> Result<()> {
-        if let Some(index) = self.addresses.iter().position(|x| x == address) {
-            // Use swap_remove for gas efficiency (doesn't preserve order but is O(1))
-            self.addresses.swap_remove(index);
-            Ok(())
-        } else {
-            err!(DiamondTokenError::AddressNotBlacklisted)
-        }
-    }
-}
+use anchor_lang::prelude::*;
+use crate::{
+    error::DiamondTokenError,
+    constants::{OPERATION_COOLDOWN, PAUSE_ALL, PAUSE_FLAG_COUNT, STATE_VERSION, UNPAUSE_COOLDOWN},
+};
+
+/// Token state account storing program configuration and state
+/// Optimized storage layout with robust security features
+#[account]
+#[derive(Debug)]
+pub struct TokenState {
+    pub version: u8,               // 1 byte - layout version, see STATE_VERSION
+    pub authority: Pubkey,         // 32 bytes
+    pub mint: Pubkey,              // 32 bytes
+    pub total_supply: u64,         // 8 bytes
+    pub max_supply: u64,           // 8 bytes
+    pub paused_flags: u8,          // 1 byte - bitmask of PAUSE_* flags
+    pub pause_timestamps: [i64; PAUSE_FLAG_COUNT], // 8 bytes per flag - when each flag was last paused
+    pub multisig: Pubkey,          // 32 bytes
+    pub vault_owner: Pubkey,       // 32 bytes - PDA that owns the vault
+    pub bump: u8,                  // 1 byte
+    pub in_operation: bool,        // 1 byte - reentrancy guard
+    pub last_operation_timestamp: i64, // 8 bytes - operation cooldown
+    pub event_sequence: u64,       // 8 bytes - sequence number of the last emitted event
+    pub reserved: [u8; 56],        // 56 bytes - room for future fields
+}
+
+impl Default for TokenState {
+    fn default() -> Self {
+        Self {
+            version: STATE_VERSION,
+            authority: Pubkey::default(),
+            mint: Pubkey::default(),
+            total_supply: 0,
+            max_supply: 0,
+            paused_flags: 0,
+            pause_timestamps: [0; PAUSE_FLAG_COUNT],
+            multisig: Pubkey::default(),
+            vault_owner: Pubkey::default(),
+            bump: 0,
+            in_operation: false,
+            last_operation_timestamp: 0,
+            event_sequence: 0,
+            reserved: [0; 56],
+        }
+    }
+}
+
+impl TokenState {
+    pub const LEN: usize = 1 + 32 + 32 + 8 + 8 + 1 + 8 * PAUSE_FLAG_COUNT + 32 + 32 + 1 + 1 + 8 + 8 + 56;
+
+    /// Check if any of the given pause flags is set
+    #[inline(always)]
+    pub fn is_paused(&self, flags: u8) -> bool {
+        self.paused_flags & flags != 0
+    }
+
+    /// Check if every operation is paused
+    #[inline(always)]
+    pub fn is_fully_paused(&self) -> bool {
+        self.paused_flags == PAUSE_ALL
+    }
+
+    /// Set pause flags, recording the pause time of each newly paused flag
+    /// Returns the flags that were not already paused
+    pub fn pause(&mut self, flags: u8, timestamp: i64) -> u8 {
+        let newly_paused = flags & PAUSE_ALL & !self.paused_flags;
+        for (index, pause_timestamp) in self.pause_timestamps.iter_mut().enumerate() {
+            if newly_paused & (1 << index) != 0 {
+                *pause_timestamp = timestamp;
+            }
+        }
+        self.paused_flags |= newly_paused;
+        newly_paused
+    }
+
+    /// Clear pause flags once UNPAUSE_COOLDOWN has elapsed for each of them
+    pub fn unpause(&mut self, flags: u8, timestamp: i64) -> Result<()> {
+        require!(flags & !self.paused_flags == 0, DiamondTokenError::NotPaused);
+        for (index, pause_timestamp) in self.pause_timestamps.iter().enumerate() {
+            if flags & (1 << index) != 0 {
+                let cooldown_elapsed = timestamp
+                    .checked_sub(*pause_timestamp)
+                    .ok_or(DiamondTokenError::MathOverflow)?;
+                require!(
+                    cooldown_elapsed >= UNPAUSE_COOLDOWN,
+                    DiamondTokenError::UnpauseCooldownNotElapsed
+                );
+            }
+        }
+        self.paused_flags &= !flags;
+        Ok(())
+    }
+
+    /// Start an operation with reentrancy protection
+    /// Enhanced timing attack protection and secure cooldown checks
+    #[inline(always)]
+    pub fn start_operation(&mut self) -> Result<()> {
+        // Check if already in an operation
+        require!(!self.in_operation, DiamondTokenError::ReentrancyNotAllowed);
+        
+        // Get current timestamp with error handling
+        let current_time = Clock::get()?.unix_timestamp;
+        
+        // Perform checked subtraction for cooldown calculation
+        let time_diff = current_time
+            .checked_sub(self.last_operation_timestamp)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+            
+        // Verify cooldown has passed
+        require!(
+            time_diff >= OPERATION_COOLDOWN,
+            DiamondTokenError::OperationCooldownNotElapsed
+        );
+
+        // Set reentrancy guard and update timestamp
+        self.in_operation = true;
+        self.last_operation_timestamp = current_time;
+        
+        Ok(())
+    }
+
+    /// End an operation and clear reentrancy guard
+    #[inline(always)]
+    pub fn end_operation(&mut self) {
+        self.in_operation = false;
+    }
+
+    /// Claim the sequence number for the next emitted event
+    /// Sequence numbers start at 1 and never repeat, so indexers can detect missed events
+    pub fn next_event_sequence(&mut self) -> Result<u64> {
+        self.event_sequence = self.event_sequence
+            .checked_add(1)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        Ok(self.event_sequence)
+    }
+
+    /// Check if the provided address is an admin
+    #[inline(always)]
+    pub fn is_admin(&self, admin: &Pubkey) -> bool {
+        self.authority == *admin
+    }
+    
+    /// Update total supply with checked math to prevent overflows
+    #[inline(always)]
+    pub fn update_total_supply_add(&mut self, amount: u64) -> Result<()> {
+        self.total_supply = self.total_supply
+            .checked_add(amount)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        Ok(())
+    }
+    
+    /// Subtract from total supply with checked math to prevent underflows
+    #[inline(always)]
+    pub fn update_total_supply_sub(&mut self, amount: u64) -> Result<()> {
+        self.total_supply = self.total_supply
+            .checked_sub(amount)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Blacklist account storing addresses that are not allowed to interact with the token
+/// Optimized for faster lookup and memory efficiency
+#[account]
+#[derive(Default, Debug)]
+pub struct Blacklist {
+    pub version: u8,            // layout version, see STATE_VERSION
+    pub addresses: Vec<Pubkey>, // up to MAX_BLACKLIST_SIZE
+    pub bump: u8,
+    pub reserved: [u8; 32],     // room for future fields
+}
+
+impl Blacklist {
+    pub fn space() -> usize {
+        8 + 1 + 4 + (32 * crate::constants::MAX_BLACKLIST_SIZE) + 1 + 32  // More explicit calculation
+    }
+    
+    /// Optimized contains check for better gas efficiency
+    #[inline(always)]
+    pub fn contains(&self, address: &Pubkey) -> bool {
+        self.addresses.contains(address)
+    }
+    
+    /// Safe add method with blacklist size validation
+    #[inline(always)]
+    pub fn add(&mut self, address: Pubkey) -> Result<()> {
+        // Check if address is already blacklisted
+        require!(
+            !self.addresses.contains(&address),
+            DiamondTokenError::AddressAlreadyBlacklisted
+        );
+        
+        // Check if blacklist is full
+        require!(
+            self.addresses.len() < crate::constants::MAX_BLACKLIST_SIZE,
+            DiamondTokenError::BlacklistFull
+        );
+        
+        // Add address to blacklist
+        self.addresses.push(address);
+        Ok(())
+    }
+    
+    /// Safe remove method with existence validation
+    #[inline(always)]
+    pub fn remove(&mut self, address: &Pubkey) -> Result<()> {
+        if let Some(index) = self.addresses.iter().position(|x| x == address) {
+            // Use swap_remove for gas efficiency (doesn't preserve order but is O(1))
+            self.addresses.swap_remove(index);
+            Ok(())
+        } else {
+            err!(DiamondTokenError::AddressNotBlacklisted)
+        }
+    }
+}
+
+/// Single proof-of-reserve check stored in the reserve history ring buffer
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReserveRecord {
+    pub slot: u64,             // 8 bytes
+    pub timestamp: i64,        // 8 bytes
+    pub total_supply: u64,     // 8 bytes
+    pub reserve_amount: u64,   // 8 bytes
+    pub ratio_bps: u64,        // 8 bytes - reserve / expected reserve in basis points
+    pub source: Pubkey,        // 32 bytes - reserve token account that was read
+}
+
+impl ReserveRecord {
+    pub const LEN: usize = 72; // 8 + 8 + 8 + 8 + 8 + 32
+}
+
+/// Reserve history account keeping the last RESERVE_HISTORY_SIZE reserve checks
+/// Entries are overwritten oldest-first once the buffer is full
+#[account]
+#[derive(Default, Debug)]
+pub struct ReserveHistory {
+    pub entries: Vec<ReserveRecord>, // up to RESERVE_HISTORY_SIZE
+    pub next_index: u16,             // slot in `entries` written by the next append
+    pub total_checks: u64,           // lifetime number of appended checks
+    pub bump: u8,
+}
+
+impl ReserveHistory {
+    pub fn space() -> usize {
+        8 + 4 + (ReserveRecord::LEN * crate::constants::RESERVE_HISTORY_SIZE) + 2 + 8 + 1
+    }
+
+    /// Append a record, overwriting the oldest entry once the buffer is full
+    pub fn push(&mut self, record: ReserveRecord) -> Result<()> {
+        let index = self.next_index as usize;
+        if self.entries.len() < crate::constants::RESERVE_HISTORY_SIZE {
+            self.entries.push(record);
+        } else {
+            self.entries[index] = record;
+        }
+
+        self.next_index = ((index + 1) % crate::constants::RESERVE_HISTORY_SIZE) as u16;
+        self.total_checks = self.total_checks
+            .checked_add(1)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Return up to `count` entries, newest first
+    pub fn recent(&self, count: usize) -> Vec<ReserveRecord> {
+        let len = self.entries.len();
+        if len == 0 {
+            return Vec::new();
+        }
+
+        let count = count.min(len);
+        let newest = (self.next_index as usize + len - 1) % len;
+
+        (0..count)
+            .map(|offset| self.entries[(newest + len - offset) % len])
+            .collect()
+    }
+}
+
+/// Reserve configuration account used by the permissionless reserve crank
+#[account]
+#[derive(Default, Debug)]
+pub struct ReserveConfig {
+    pub reserve_vault: Pubkey,       // 32 bytes - USDC vault backing the supply
+    pub min_reserve_ratio_bps: u64,  // 8 bytes - pause threshold in basis points
+    pub bump: u8,                    // 1 byte
+}
+
+impl ReserveConfig {
+    pub const LEN: usize = 41; // 32 + 8 + 1
+
+    /// Check whether a reserve ratio falls below the configured threshold
+    #[inline(always)]
+    pub fn is_shortfall(&self, ratio_bps: u64) -> bool {
+        ratio_bps < self.min_reserve_ratio_bps
+    }
+}
+
+/// Treasury account holding the registered withdrawal destination
+#[account]
+#[derive(Default, Debug)]
+pub struct Treasury {
+    pub destination: Pubkey,   // 32 bytes - USDC token account receiving withdrawals
+    pub total_withdrawn: u64,  // 8 bytes - lifetime USDC withdrawn from the reserve vault
+    pub bump: u8,              // 1 byte
+}
+
+impl Treasury {
+    pub const LEN: usize = 41; // 32 + 8 + 1
+
+    /// Record a withdrawal with checked math to prevent overflows
+    #[inline(always)]
+    pub fn record_withdrawal(&mut self, amount: u64) -> Result<()> {
+        self.total_withdrawn = self.total_withdrawn
+            .checked_add(amount)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Order configuration account holding the merchant role
+#[account]
+#[derive(Default, Debug)]
+pub struct OrderConfig {
+    pub merchant: Pubkey,        // 32 bytes - role allowed to update order status
+    pub reserved: [u8; 8],       // 8 bytes - padding left by the retired global order counter
+    pub bump: u8,                // 1 byte
+}
+
+impl OrderConfig {
+    pub const LEN: usize = 41; // 32 + 8 + 1
+}
+
+/// Per-buyer order counter, so purchases from different buyers never
+/// contend for the same order number or write-lock a shared account
+#[account]
+#[derive(Default, Debug)]
+pub struct BuyerOrders {
+    pub buyer: Pubkey,           // 32 bytes
+    pub next_order_number: u64,  // 8 bytes - sequential number of the buyer's next order
+    pub bump: u8,                // 1 byte
+}
+
+impl BuyerOrders {
+    pub const LEN: usize = 41; // 32 + 8 + 1
+
+    /// Take the buyer's next sequential order number with checked math
+    #[inline(always)]
+    pub fn take_order_number(&mut self) -> Result<u64> {
+        let order_number = self.next_order_number;
+        self.next_order_number = self.next_order_number
+            .checked_add(1)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        Ok(order_number)
+    }
+}
+
+/// Fulfillment status of an order
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OrderStatus {
+    #[default]
+    Created,
+    Confirmed,
+    Shipped,
+    Delivered,
+    Cancelled,
+}
+
+impl OrderStatus {
+    /// Allowed transitions: Created -> Confirmed -> Shipped -> Delivered,
+    /// with cancellation possible until the order is delivered
+    pub fn can_transition_to(&self, next: OrderStatus) -> bool {
+        matches!(
+            (self, next),
+            (OrderStatus::Created, OrderStatus::Confirmed)
+                | (OrderStatus::Confirmed, OrderStatus::Shipped)
+                | (OrderStatus::Shipped, OrderStatus::Delivered)
+                | (OrderStatus::Created, OrderStatus::Cancelled)
+                | (OrderStatus::Confirmed, OrderStatus::Cancelled)
+                | (OrderStatus::Shipped, OrderStatus::Cancelled)
+        )
+    }
+
+    /// Delivered and Cancelled orders cannot move any further
+    #[inline(always)]
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, OrderStatus::Delivered | OrderStatus::Cancelled)
+    }
+}
+
+/// Where the funds of an order were sent
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OrderSettlement {
+    /// Program vault owned by the vault_owner PDA
+    #[default]
+    Vault,
+    /// Per-order buyer-protection escrow
+    Escrow,
+    /// Registered marketplace merchant, minus the platform fee
+    Merchant,
+}
+
+/// Order account created for every item purchase
+#[account]
+#[derive(Default, Debug)]
+pub struct Order {
+    pub order_number: u64,            // 8 bytes
+    pub buyer: Pubkey,                // 32 bytes
+    pub item_id: String,              // 4 + MAX_ITEM_ID_LEN bytes
+    pub amount: u64,                  // 8 bytes
+    pub shipping_info_hash: [u8; 32], // 32 bytes - hash of off-chain shipping details
+    pub status: OrderStatus,          // 1 byte
+    pub created_at: i64,              // 8 bytes
+    pub updated_at: i64,              // 8 bytes
+    pub refunded_amount: u64,         // 8 bytes - total DREAMT refunded to the buyer
+    pub settlement: OrderSettlement,  // 1 byte - where the purchase funds were sent
+    pub points_issued: u64,           // 8 bytes - loyalty points issued for the order
+    pub bump: u8,                     // 1 byte
+}
+
+impl Order {
+    pub const LEN: usize = 8 + 32 + 4 + crate::constants::MAX_ITEM_ID_LEN + 8 + 32 + 1 + 8 + 8 + 8 + 1 + 8 + 1;
+
+    /// Loyalty points issued for the share of the order refunded since
+    /// `refunded_before`, computed on cumulative totals so rounding never drifts
+    pub fn points_to_revoke(&self, refunded_before: u64) -> Result<u64> {
+        let points_for = |refunded: u64| -> Result<u64> {
+            let points = (self.points_issued as u128)
+                .checked_mul(refunded as u128)
+                .ok_or(DiamondTokenError::MathOverflow)?
+                / self.amount as u128;
+            Ok(points as u64)
+        };
+        points_for(self.refunded_amount)?
+            .checked_sub(points_for(refunded_before)?)
+            .ok_or(DiamondTokenError::MathOverflow.into())
+    }
+
+    /// Record a refund, refusing to refund more than was paid
+    pub fn record_refund(&mut self, amount: u64) -> Result<()> {
+        let refunded_amount = self.refunded_amount
+            .checked_add(amount)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        require!(
+            refunded_amount <= self.amount,
+            DiamondTokenError::RefundExceedsPayment
+        );
+        self.refunded_amount = refunded_amount;
+        Ok(())
+    }
+
+    /// Check whether the full payment has been refunded
+    #[inline(always)]
+    pub fn is_fully_refunded(&self) -> bool {
+        self.refunded_amount == self.amount
+    }
+
+    /// Move the order to a new status if the transition is allowed
+    pub fn transition(&mut self, next: OrderStatus, timestamp: i64) -> Result<()> {
+        require!(
+            self.status.can_transition_to(next),
+            DiamondTokenError::InvalidOrderStatusTransition
+        );
+        self.status = next;
+        self.updated_at = timestamp;
+        Ok(())
+    }
+}
+
+/// Catalog configuration account holding the catalog-manager role
+#[account]
+#[derive(Default, Debug)]
+pub struct CatalogConfig {
+    pub manager: Pubkey, // 32 bytes - role allowed to create, update and remove items
+    pub bump: u8,        // 1 byte
+}
+
+impl CatalogConfig {
+    pub const LEN: usize = 33; // 32 + 1
+}
+
+/// Currency a catalog price is denominated in
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PriceCurrency {
+    /// Price in raw DREAMT units
+    #[default]
+    Dreamt,
+    /// Price in raw USDC units, converted at TOKEN_PRICE_USDC
+    Usd,
+}
+
+/// Single line of a purchase_items cart
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CartLine {
+    pub item_id: String,
+    pub quantity: u64,
+}
+
+/// Catalog item account holding the authoritative price and stock of an item
+#[account]
+#[derive(Default, Debug)]
+pub struct CatalogItem {
+    pub item_id: String,               // 4 + MAX_ITEM_ID_LEN bytes
+    pub price: u64,                    // 8 bytes
+    pub price_currency: PriceCurrency, // 1 byte
+    pub stock: u64,                    // 8 bytes
+    pub is_active: bool,               // 1 byte
+    pub merchant: Pubkey,              // 32 bytes
+    pub bump: u8,                      // 1 byte
+}
+
+impl CatalogItem {
+    pub const LEN: usize = 4 + crate::constants::MAX_ITEM_ID_LEN + 8 + 1 + 8 + 1 + 32 + 1;
+
+    /// Price of one unit in raw DREAMT, rounding USD prices up
+    pub fn price_in_tokens(&self) -> Result<u64> {
+        match self.price_currency {
+            PriceCurrency::Dreamt => Ok(self.price),
+            PriceCurrency::Usd => {
+                let price_usdc = crate::constants::TOKEN_PRICE_USDC;
+                Ok(self.price
+                    .checked_add(price_usdc - 1)
+                    .ok_or(DiamondTokenError::MathOverflow)?
+                    / price_usdc)
+            }
+        }
+    }
+
+    /// Decrement stock for a sold quantity
+    #[inline(always)]
+    pub fn take_stock(&mut self, quantity: u64) -> Result<()> {
+        self.stock = self.stock
+            .checked_sub(quantity)
+            .ok_or(DiamondTokenError::OutOfStock)?;
+        Ok(())
+    }
+
+    /// Return a refunded quantity to stock
+    #[inline(always)]
+    pub fn restock(&mut self, quantity: u64) -> Result<()> {
+        self.stock = self.stock
+            .checked_add(quantity)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Escrow configuration account holding the arbitrator role and release timeout
+#[account]
+#[derive(Default, Debug)]
+pub struct EscrowConfig {
+    pub arbitrator: Pubkey,    // 32 bytes - role allowed to resolve disputes
+    pub release_timeout: i64,  // 8 bytes - seconds after funding before anyone can release
+    pub bump: u8,              // 1 byte
+}
+
+impl EscrowConfig {
+    pub const LEN: usize = 41; // 32 + 8 + 1
+}
+
+/// Lifecycle of a buyer-protection escrow
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EscrowStatus {
+    #[default]
+    Funded,
+    Disputed,
+    Released,
+    Resolved,
+}
+
+/// Per-order escrow holding purchase funds until release or dispute resolution
+#[account]
+#[derive(Default, Debug)]
+pub struct Escrow {
+    pub order_number: u64,     // 8 bytes
+    pub buyer: Pubkey,         // 32 bytes
+    pub merchant: Pubkey,      // 32 bytes
+    pub amount: u64,           // 8 bytes
+    pub release_after: i64,    // 8 bytes
+    pub status: EscrowStatus,  // 1 byte
+    pub vault_bump: u8,        // 1 byte - bump of the escrow token account
+    pub bump: u8,              // 1 byte
+}
+
+impl Escrow {
+    pub const LEN: usize = 91; // 8 + 32 + 32 + 8 + 8 + 1 + 1 + 1
+}
+
+/// Marketplace configuration account holding the platform fee
+#[account]
+#[derive(Default, Debug)]
+pub struct MarketplaceConfig {
+    pub platform_fee_bps: u64, // 8 bytes - fee sent to the vault on merchant sales
+    pub bump: u8,              // 1 byte
+}
+
+impl MarketplaceConfig {
+    pub const LEN: usize = 9; // 8 + 1
+
+    /// Split a sale into (platform fee, merchant amount), rounding the fee down
+    pub fn split_sale(&self, amount: u64) -> Result<(u64, u64)> {
+        let fee = (amount as u128)
+            .checked_mul(self.platform_fee_bps as u128)
+            .ok_or(DiamondTokenError::MathOverflow)?
+            / (crate::constants::BPS_DENOMINATOR as u128);
+        let fee = u64::try_from(fee).map_err(|_| DiamondTokenError::MathOverflow)?;
+        let merchant_amount = amount
+            .checked_sub(fee)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        Ok((fee, merchant_amount))
+    }
+}
+
+/// Registered marketplace merchant with its receiving account and sales totals
+#[account]
+#[derive(Default, Debug)]
+pub struct Merchant {
+    pub authority: Pubkey,          // 32 bytes - merchant key referenced by catalog items
+    pub receiving_account: Pubkey,  // 32 bytes - DREAMT token account receiving sales
+    pub is_active: bool,            // 1 byte - suspended merchants cannot sell
+    pub total_sales: u64,           // 8 bytes - gross DREAMT sales
+    pub total_fees: u64,            // 8 bytes - platform fees taken from sales
+    pub order_count: u64,           // 8 bytes
+    pub bump: u8,                   // 1 byte
+}
+
+impl Merchant {
+    pub const LEN: usize = 90; // 32 + 32 + 1 + 8 + 8 + 8 + 1
+
+    /// Record a sale with checked math to prevent overflows
+    pub fn record_sale(&mut self, amount: u64, fee: u64) -> Result<()> {
+        self.total_sales = self.total_sales
+            .checked_add(amount)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        self.total_fees = self.total_fees
+            .checked_add(fee)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        self.order_count = self.order_count
+            .checked_add(1)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Coupon configuration account holding the marketing role
+#[account]
+#[derive(Default, Debug)]
+pub struct CouponConfig {
+    pub marketing: Pubkey, // 32 bytes - role allowed to create coupons
+    pub bump: u8,          // 1 byte
+}
+
+impl CouponConfig {
+    pub const LEN: usize = 33; // 32 + 1
+}
+
+/// How a coupon discount is applied
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DiscountKind {
+    /// Discount in basis points of the price
+    #[default]
+    Percent,
+    /// Fixed discount in raw DREAMT units
+    Fixed,
+}
+
+/// Discount coupon keyed by the hash of its promo code
+#[account]
+#[derive(Default, Debug)]
+pub struct Coupon {
+    pub code_hash: [u8; 32],     // 32 bytes - sha256 of the promo code
+    pub kind: DiscountKind,      // 1 byte
+    pub value: u64,              // 8 bytes - basis points or raw DREAMT
+    pub max_uses: u32,           // 4 bytes
+    pub uses: u32,               // 4 bytes
+    pub expires_at: i64,         // 8 bytes
+    pub item_id: Option<String>, // 1 + 4 + MAX_ITEM_ID_LEN bytes - optional item restriction
+    pub redeemer: Pubkey,        // 32 bytes - only buyer allowed to redeem
+    pub bump: u8,                // 1 byte
+}
+
+impl Coupon {
+    pub const LEN: usize = 32 + 1 + 8 + 4 + 4 + 8 + 1 + 4 + crate::constants::MAX_ITEM_ID_LEN + 32 + 1;
+
+    /// Discount for a price, always leaving at least one raw unit to pay
+    pub fn discount_for(&self, price: u64) -> Result<u64> {
+        let discount = match self.kind {
+            DiscountKind::Percent => {
+                let discount = (price as u128)
+                    .checked_mul(self.value as u128)
+                    .ok_or(DiamondTokenError::MathOverflow)?
+                    / (crate::constants::BPS_DENOMINATOR as u128);
+                u64::try_from(discount).map_err(|_| DiamondTokenError::MathOverflow)?
+            }
+            DiscountKind::Fixed => self.value,
+        };
+        Ok(discount.min(price.saturating_sub(1)))
+    }
+
+    /// Validate the coupon for an item and buyer and consume one use
+    pub fn redeem(&mut self, item_id: &str, user: &Pubkey, current_time: i64) -> Result<()> {
+        require!(current_time < self.expires_at, DiamondTokenError::CouponExpired);
+        require!(self.uses < self.max_uses, DiamondTokenError::CouponExhausted);
+        if let Some(restricted_item) = &self.item_id {
+            require!(restricted_item == item_id, DiamondTokenError::CouponNotApplicable);
+        }
+        require!(&self.redeemer == user, DiamondTokenError::CouponNotApplicable);
+        self.uses += 1;
+        Ok(())
+    }
+}
+
+/// Loyalty configuration account holding the points mint and issuance rates
+/// The account is also the mint authority of the non-transferable points mint
+#[account]
+#[derive(Default, Debug)]
+pub struct LoyaltyConfig {
+    pub points_mint: Pubkey,       // 32 bytes - Token-2022 mint with the NonTransferable extension
+    pub purchase_rate_bps: u64,    // 8 bytes - points per DREAMT spent on purchases, in basis points
+    pub mint_rate_bps: u64,        // 8 bytes - points per DREAMT minted by users, 0 disables
+    pub points_per_credit: u64,    // 8 bytes - points burned per raw DREAMT of purchase credit
+    pub bump: u8,                  // 1 byte
+}
+
+impl LoyaltyConfig {
+    pub const LEN: usize = 57; // 32 + 8 + 8 + 8 + 1
+
+    /// Points earned for `amount` DREAMT at a basis-point rate, rounded down
+    pub fn points_for(amount: u64, rate_bps: u64) -> Result<u64> {
+        let points = (amount as u128)
+            .checked_mul(rate_bps as u128)
+            .ok_or(DiamondTokenError::MathOverflow)?
+            / (crate::constants::BPS_DENOMINATOR as u128);
+        u64::try_from(points).map_err(|_| error!(DiamondTokenError::MathOverflow))
+    }
+}
+
+/// Per-user purchase credit earned by redeeming loyalty points
+#[account]
+#[derive(Default, Debug)]
+pub struct PointsCredit {
+    pub user: Pubkey,       // 32 bytes
+    pub balance: u64,       // 8 bytes - raw DREAMT discount available on future purchases
+    pub points_debt: u64,   // 8 bytes - refunded points the user could not cover, paid off on redemption
+    pub bump: u8,           // 1 byte
+}
+
+impl PointsCredit {
+    pub const LEN: usize = 49; // 32 + 8 + 8 + 1
+
+    /// Charge points a refund could not burn: take the matching credit, rounded up,
+    /// and carry whatever the balance cannot cover as points debt
+    /// Returns the credit taken
+    pub fn charge_points(&mut self, points: u64, points_per_credit: u64) -> Result<u64> {
+        require!(points_per_credit > 0, DiamondTokenError::InvalidLoyaltyRate);
+        let credit_taken = self.balance.min(points.div_ceil(points_per_credit));
+        self.balance -= credit_taken;
+        let points_covered = credit_taken
+            .checked_mul(points_per_credit)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        self.points_debt = self.points_debt
+            .checked_add(points.saturating_sub(points_covered))
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        Ok(credit_taken)
+    }
+
+    /// Pay down the points debt out of points being redeemed
+    /// Returns the points applied to the debt
+    pub fn settle_debt(&mut self, points: u64) -> u64 {
+        let paid = self.points_debt.min(points);
+        self.points_debt -= paid;
+        paid
+    }
+
+    /// Use credit against a price, always leaving at least one raw unit to pay
+    pub fn use_credit(&mut self, price: u64) -> u64 {
+        let used = self.balance.min(price.saturating_sub(1));
+        self.balance -= used;
+        used
+    }
+}
+
+/// A staking tier: stakers at or above `min_stake` receive its benefits
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StakeTier {
+    pub min_stake: u64,        // 8 bytes - minimum staked DREAMT, 0 disables the tier
+    pub discount_bps: u64,     // 8 bytes - purchase_item discount
+    pub mint_limit_bps: u64,   // 8 bytes - multiplier on the mint_by_user purchase limit
+}
+
+impl StakeTier {
+    pub const LEN: usize = 24; // 8 + 8 + 8
+}
+
+/// Staking configuration account; also the authority of the pooled stake vault
+#[account]
+#[derive(Default, Debug)]
+pub struct StakeConfig {
+    pub unbonding_period: i64,                                      // 8 bytes - seconds before unstaked tokens can be withdrawn
+    pub tiers: [StakeTier; crate::constants::STAKE_TIER_COUNT],     // 24 * STAKE_TIER_COUNT bytes
+    pub bump: u8,                                                   // 1 byte
+}
+
+impl StakeConfig {
+    pub const LEN: usize = 8 + StakeTier::LEN * crate::constants::STAKE_TIER_COUNT + 1;
+
+    /// Highest enabled tier reached by `staked_amount`, with its index
+    pub fn tier_for(&self, staked_amount: u64) -> Option<(u8, StakeTier)> {
+        self.tiers
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, tier)| tier.min_stake > 0 && staked_amount >= tier.min_stake)
+            .map(|(index, tier)| (index as u8, *tier))
+    }
+}
+
+/// Per-user stake account
+/// Tokens being unbonded no longer count towards the user's tier
+#[account]
+#[derive(Default, Debug)]
+pub struct Stake {
+    pub user: Pubkey,              // 32 bytes
+    pub amount: u64,               // 8 bytes - actively staked
+    pub unbonding_amount: u64,     // 8 bytes - requested for withdrawal
+    pub unbonding_ends_at: i64,    // 8 bytes - when unbonding tokens can be withdrawn
+    pub bump: u8,                  // 1 byte
+}
+
+impl Stake {
+    pub const LEN: usize = 57; // 32 + 8 + 8 + 8 + 1
+
+    /// Move staked tokens into unbonding; a new request restarts the unbonding period
+    pub fn request_unstake(&mut self, amount: u64, unbonding_ends_at: i64) -> Result<()> {
+        require!(amount > 0, DiamondTokenError::InvalidAmount);
+        self.amount = self.amount
+            .checked_sub(amount)
+            .ok_or(DiamondTokenError::InsufficientStake)?;
+        self.unbonding_amount = self.unbonding_amount
+            .checked_add(amount)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        self.unbonding_ends_at = unbonding_ends_at;
+        Ok(())
+    }
+}
+
+/// Referral configuration account
+#[account]
+#[derive(Default, Debug)]
+pub struct ReferralConfig {
+    pub reward_bps: u64,    // 8 bytes - referrer USDC share of each referred mint
+    pub bump: u8,           // 1 byte
+}
+
+impl ReferralConfig {
+    pub const LEN: usize = 9; // 8 + 1
+
+    /// USDC reward for a referred mint, a share of the payment
+    pub fn reward_for(&self, payment_amount: u64) -> Result<u64> {
+        let reward = (payment_amount as u128)
+            .checked_mul(self.reward_bps as u128)
+            .ok_or(DiamondTokenError::MathOverflow)?
+            / (crate::constants::BPS_DENOMINATOR as u128);
+        u64::try_from(reward).map_err(|_| error!(DiamondTokenError::MathOverflow))
+    }
+
+    /// Largest payment whose reward still fits in `budget` alongside it
+    pub fn payment_within(&self, budget: u64) -> Result<u64> {
+        let bps = crate::constants::BPS_DENOMINATOR as u128;
+        let payment = (budget as u128)
+            .checked_mul(bps)
+            .ok_or(DiamondTokenError::MathOverflow)?
+            / (bps + self.reward_bps as u128);
+        u64::try_from(payment).map_err(|_| error!(DiamondTokenError::MathOverflow))
+    }
+}
+
+/// Registered referrer with lifetime referral totals
+#[account]
+#[derive(Default, Debug)]
+pub struct Referral {
+    pub referrer: Pubkey,           // 32 bytes
+    pub total_referred: u64,        // 8 bytes - USDC paid by referred mints
+    pub total_usdc_rewards: u64,    // 8 bytes
+    pub referral_count: u64,        // 8 bytes
+    pub bump: u8,                   // 1 byte
+}
+
+impl Referral {
+    pub const LEN: usize = 57; // 32 + 8 + 8 + 8 + 1
+
+    /// Record a referred mint with checked math to prevent overflows
+    pub fn record_referral(&mut self, payment_amount: u64, reward: u64) -> Result<()> {
+        self.total_referred = self.total_referred
+            .checked_add(payment_amount)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        self.total_usdc_rewards = self.total_usdc_rewards
+            .checked_add(reward)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        self.referral_count = self.referral_count
+            .checked_add(1)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Purchase intent signed off-chain by a user and submitted by a relayer
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PurchaseIntent {
+    pub amount: u64,          // tokens to mint
+    pub max_payment: u64,     // USDC slippage bound
+    pub payment_mint: Pubkey, // mint the user agrees to pay in, the reserve vault mint
+    pub nonce: u64,           // must equal the user's next intent nonce
+    pub expiry: i64,          // unix timestamp after which the intent is rejected
+}
+
+impl PurchaseIntent {
+    /// Message the user signs: domain, program, user, then the serialized intent
+    pub fn message(&self, program_id: &Pubkey, user: &Pubkey) -> Result<Vec<u8>> {
+        let mut message = Vec::with_capacity(crate::constants::PURCHASE_INTENT_DOMAIN.len() + 128);
+        message.extend_from_slice(crate::constants::PURCHASE_INTENT_DOMAIN);
+        message.extend_from_slice(program_id.as_ref());
+        message.extend_from_slice(user.as_ref());
+        self.serialize(&mut message)?;
+        Ok(message)
+    }
+}
+
+/// Per-user nonce account preventing purchase intent replay
+#[account]
+#[derive(Default, Debug)]
+pub struct IntentNonce {
+    pub user: Pubkey,      // 32 bytes
+    pub next_nonce: u64,   // 8 bytes
+    pub bump: u8,          // 1 byte
+}
+
+impl IntentNonce {
+    pub const LEN: usize = 41; // 32 + 8 + 1
+
+    /// Consume `nonce` if it is the next expected one
+    pub fn consume(&mut self, nonce: u64) -> Result<()> {
+        require!(nonce == self.next_nonce, DiamondTokenError::InvalidIntentNonce);
+        self.next_nonce = self.next_nonce
+            .checked_add(1)
+            .ok_or(DiamondTokenError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Guardian keys allowed to pause without the multisig, and the last guardian pause
+#[account]
+#[derive(Default, Debug)]
+pub struct GuardianSet {
+    pub guardians: Vec<Pubkey>,       // 4 + 32 * MAX_GUARDIANS bytes
+    pub last_pause_by: Pubkey,        // 32 bytes - guardian that triggered the last guardian pause
+    pub last_pause_reason: u16,       // 2 bytes - off-chain reason code
+    pub last_pause_timestamp: i64,    // 8 bytes
+    pub bump: u8,                     // 1 byte
+}
+
+impl GuardianSet {
+    pub const LEN: usize = 4 + 32 * crate::constants::MAX_GUARDIANS + 32 + 2 + 8 + 1;
+
+    /// Check if the provided address is a guardian
+    #[inline(always)]
+    pub fn is_guardian(&self, address: &Pubkey) -> bool {
+        self.guardians.contains(address)
+    }
+
+    /// Record a guardian pause
+    pub fn record_pause(&mut self, guardian: Pubkey, reason_code: u16, timestamp: i64) {
+        self.last_pause_by = guardian;
+        self.last_pause_reason = reason_code;
+        self.last_pause_timestamp = timestamp;
+    }
+}
+
+/// TokenState layout before versioning (version 0), read by migrate_state
+#[derive(AnchorDeserialize)]
+pub struct LegacyTokenState {
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    pub total_supply: u64,
+    pub max_supply: u64,
+    pub is_paused: bool,
+    pub last_pause_timestamp: i64,
+    pub multisig: Pubkey,
+    pub vault_owner: Pubkey,
+    pub bump: u8,
+    pub in_operation: bool,
+    pub last_operation_timestamp: i64,
+}
+
+impl LegacyTokenState {
+    /// Allocated size of a version 0 account, including the discriminator
+    pub const SPACE: usize = 8 + 163;
+
+    /// Upgrade to the current layout; a legacy pause pauses every operation
+    pub fn upgrade(self) -> TokenState {
+        let (paused_flags, pause_timestamp) = if self.is_paused {
+            (PAUSE_ALL, self.last_pause_timestamp)
+        } else {
+            (0, 0)
+        };
+        TokenState {
+            version: STATE_VERSION,
+            authority: self.authority,
+            mint: self.mint,
+            total_supply: self.total_supply,
+            max_supply: self.max_supply,
+            paused_flags,
+            pause_timestamps: [pause_timestamp; PAUSE_FLAG_COUNT],
+            multisig: self.multisig,
+            vault_owner: self.vault_owner,
+            bump: self.bump,
+            in_operation: self.in_operation,
+            last_operation_timestamp: self.last_operation_timestamp,
+            event_sequence: 0,
+            reserved: [0; 56],
+        }
+    }
+}
+
+/// Blacklist layout before versioning (version 0), read by migrate_state
+#[derive(AnchorDeserialize)]
+pub struct LegacyBlacklist {
+    pub addresses: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl LegacyBlacklist {
+    /// Allocated size of a version 0 account (the old space() already counted the discriminator once)
+    pub const SPACE: usize = 8 + 8 + 4 + (32 * crate::constants::MAX_BLACKLIST_SIZE) + 1;
+
+    /// Upgrade to the current layout
+    pub fn upgrade(self) -> Blacklist {
+        Blacklist {
+            version: STATE_VERSION,
+            addresses: self.addresses,
+            bump: self.bump,
+            reserved: [0; 32],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::*;
+
+    fn record(slot: u64) -> ReserveRecord {
+        ReserveRecord {
+            slot,
+            ..ReserveRecord::default()
+        }
+    }
+
+    fn order(amount: u64, points_issued: u64) -> Order {
+        Order {
+            amount,
+            points_issued,
+            ..Order::default()
+        }
+    }
+
+    fn coupon(kind: DiscountKind, value: u64, redeemer: Pubkey) -> Coupon {
+        Coupon {
+            kind,
+            value,
+            max_uses: 2,
+            expires_at: 1_000,
+            redeemer,
+            ..Coupon::default()
+        }
+    }
+
+    #[test]
+    fn reserve_history_wraps_around_newest_first() {
+        let mut history = ReserveHistory::default();
+        assert!(history.recent(4).is_empty());
+
+        for slot in 0..(RESERVE_HISTORY_SIZE as u64 + 3) {
+            history.push(record(slot)).unwrap();
+        }
+        assert_eq!(history.entries.len(), RESERVE_HISTORY_SIZE);
+        assert_eq!(history.next_index, 3);
+        assert_eq!(history.total_checks, RESERVE_HISTORY_SIZE as u64 + 3);
+
+        let slots: Vec<u64> = history.recent(4).iter().map(|record| record.slot).collect();
+        let newest = RESERVE_HISTORY_SIZE as u64 + 2;
+        assert_eq!(slots, vec![newest, newest - 1, newest - 2, newest - 3]);
+        assert_eq!(history.recent(usize::MAX).last().unwrap().slot, 3);
+    }
+
+    #[test]
+    fn order_status_follows_fulfillment_order() {
+        use OrderStatus::*;
+        assert!(Created.can_transition_to(Confirmed));
+        assert!(Confirmed.can_transition_to(Shipped));
+        assert!(Shipped.can_transition_to(Delivered));
+        assert!(Shipped.can_transition_to(Cancelled));
+        assert!(!Created.can_transition_to(Delivered));
+        assert!(!Shipped.can_transition_to(Confirmed));
+        for terminal in [Delivered, Cancelled] {
+            assert!(terminal.is_terminal());
+            for next in [Created, Confirmed, Shipped, Delivered, Cancelled] {
+                assert!(!terminal.can_transition_to(next));
+            }
+        }
+    }
+
+    #[test]
+    fn order_transition_rejects_settling_a_closed_order() {
+        let mut order = order(100, 0);
+        order.status = OrderStatus::Cancelled;
+        assert_eq!(
+            order.transition(OrderStatus::Delivered, 5).unwrap_err(),
+            DiamondTokenError::InvalidOrderStatusTransition.into()
+        );
+
+        order.status = OrderStatus::Shipped;
+        order.transition(OrderStatus::Delivered, 5).unwrap();
+        assert_eq!(order.updated_at, 5);
+        assert!(order.transition(OrderStatus::Delivered, 6).is_err());
+    }
+
+    #[test]
+    fn coupon_discount_leaves_one_unit_to_pay() {
+        let redeemer = Pubkey::new_unique();
+        let percent = coupon(DiscountKind::Percent, 2_500, redeemer);
+        assert_eq!(percent.discount_for(1_000).unwrap(), 250);
+        assert_eq!(percent.discount_for(1).unwrap(), 0);
+
+        let fixed = coupon(DiscountKind::Fixed, 500, redeemer);
+        assert_eq!(fixed.discount_for(2_000).unwrap(), 500);
+        assert_eq!(fixed.discount_for(300).unwrap(), 299);
+    }
+
+    #[test]
+    fn coupon_redeems_only_for_its_redeemer_until_exhausted() {
+        let redeemer = Pubkey::new_unique();
+        let mut coupon = Coupon {
+            item_id: Some("ring".to_string()),
+            ..coupon(DiscountKind::Fixed, 10, redeemer)
+        };
+
+        assert_eq!(
+            coupon.redeem("ring", &Pubkey::new_unique(), 0).unwrap_err(),
+            DiamondTokenError::CouponNotApplicable.into()
+        );
+        assert_eq!(
+            coupon.redeem("necklace", &redeemer, 0).unwrap_err(),
+            DiamondTokenError::CouponNotApplicable.into()
+        );
+        assert_eq!(
+            coupon.redeem("ring", &redeemer, 1_000).unwrap_err(),
+            DiamondTokenError::CouponExpired.into()
+        );
+
+        coupon.redeem("ring", &redeemer, 0).unwrap();
+        coupon.redeem("ring", &redeemer, 0).unwrap();
+        assert_eq!(coupon.uses, 2);
+        assert_eq!(
+            coupon.redeem("ring", &redeemer, 0).unwrap_err(),
+            DiamondTokenError::CouponExhausted.into()
+        );
+    }
+
+    #[test]
+    fn referral_payment_leaves_room_for_the_reward() {
+        let config = ReferralConfig {
+            reward_bps: 500,
+            bump: 0,
+        };
+        let payment = config.payment_within(10_500).unwrap();
+        assert_eq!(payment, 10_000);
+        assert_eq!(config.reward_for(payment).unwrap(), 500);
+
+        let payment = config.payment_within(999).unwrap();
+        assert!(payment + config.reward_for(payment).unwrap() <= 999);
+
+        let free = ReferralConfig::default();
+        assert_eq!(free.payment_within(u64::MAX).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn points_to_revoke_tracks_cumulative_refunds() {
+        let mut order = order(3, 100);
+        order.record_refund(1).unwrap();
+        assert_eq!(order.points_to_revoke(0).unwrap(), 33);
+        order.record_refund(1).unwrap();
+        assert_eq!(order.points_to_revoke(1).unwrap(), 33);
+        order.record_refund(1).unwrap();
+        assert_eq!(order.points_to_revoke(2).unwrap(), 34);
+        assert!(order.is_fully_refunded());
+        assert_eq!(
+            order.record_refund(1).unwrap_err(),
+            DiamondTokenError::RefundExceedsPayment.into()
+        );
+    }
+
+    #[test]
+    fn points_to_revoke_rejects_a_later_baseline() {
+        let mut order = order(10, 100);
+        order.record_refund(2).unwrap();
+        assert_eq!(
+            order.points_to_revoke(5).unwrap_err(),
+            DiamondTokenError::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn points_credit_charges_credit_then_carries_debt() {
+        let mut credit = PointsCredit {
+            balance: 3,
+            ..PointsCredit::default()
+        };
+        // 250 points at 100 points per credit round up to 3 credit
+        assert_eq!(credit.charge_points(250, 100).unwrap(), 3);
+        assert_eq!((credit.balance, credit.points_debt), (0, 0));
+
+        credit.balance = 1;
+        assert_eq!(credit.charge_points(250, 100).unwrap(), 1);
+        assert_eq!((credit.balance, credit.points_debt), (0, 150));
+        assert!(credit.charge_points(1, 0).is_err());
+
+        assert_eq!(credit.settle_debt(100), 100);
+        assert_eq!(credit.settle_debt(100), 50);
+        assert_eq!(credit.points_debt, 0);
+        assert_eq!(credit.settle_debt(100), 0);
+    }
+
+    #[test]
+    fn unpause_waits_for_each_flag_cooldown() {
+        let mut state = TokenState::default();
+        assert_eq!(state.pause(PAUSE_MINT, 100), PAUSE_MINT);
+        assert_eq!(state.pause(PAUSE_MINT | PAUSE_BURN, 200), PAUSE_BURN);
+        assert_eq!(state.pause(0xff, 300), PAUSE_ALL & !(PAUSE_MINT | PAUSE_BURN));
+        assert!(state.is_fully_paused());
+
+        assert_eq!(
+            state.unpause(PAUSE_MINT, 100 + UNPAUSE_COOLDOWN - 1).unwrap_err(),
+            DiamondTokenError::UnpauseCooldownNotElapsed.into()
+        );
+        state.unpause(PAUSE_MINT, 100 + UNPAUSE_COOLDOWN).unwrap();
+        assert!(!state.is_paused(PAUSE_MINT));
+        assert!(state.unpause(PAUSE_BURN, 100 + UNPAUSE_COOLDOWN).is_err());
+        assert_eq!(
+            state.unpause(PAUSE_MINT, 1_000_000).unwrap_err(),
+            DiamondTokenError::NotPaused.into()
+        );
+    }
+
+    #[test]
+    fn reserve_shortfall_pauses_only_minting() {
+        let mut state = TokenState::default();
+        state.pause(RESERVE_SHORTFALL_PAUSE, 0);
+        // purchase_item_with_usdc is gated on PAUSE_PURCHASE | PAUSE_MINT
+        assert!(state.is_paused(PAUSE_PURCHASE | PAUSE_MINT));
+        assert!(!state.is_paused(
+            PAUSE_PURCHASE | PAUSE_BURN | PAUSE_REDEEM | PAUSE_TRANSFER | PAUSE_ADMIN_CONFIG
+        ));
+    }
+
+    #[test]
+    fn event_sequence_starts_at_one_and_never_wraps() {
+        let mut state = TokenState::default();
+        assert_eq!(state.next_event_sequence().unwrap(), 1);
+        assert_eq!(state.next_event_sequence().unwrap(), 2);
+
+        state.event_sequence = u64::MAX;
+        assert!(state.next_event_sequence().is_err());
+        assert_eq!(state.event_sequence, u64::MAX);
+    }
+
+    #[test]
+    fn legacy_accounts_fit_their_allocations() {
+        let addresses: Vec<Pubkey> = (0..MAX_BLACKLIST_SIZE).map(|_| Pubkey::new_unique()).collect();
+        let legacy_data = (addresses.clone(), 7u8).try_to_vec().unwrap();
+        assert!(8 + legacy_data.len() <= LegacyBlacklist::SPACE);
+        assert!(LegacyBlacklist::SPACE <= Blacklist::space());
+
+        let blacklist = LegacyBlacklist::deserialize(&mut legacy_data.as_slice()).unwrap().upgrade();
+        assert_eq!(blacklist.addresses, addresses);
+        assert_eq!((blacklist.version, blacklist.bump), (STATE_VERSION, 7));
+        let mut data = Vec::new();
+        blacklist.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), Blacklist::space());
+
+        let mut data = Vec::new();
+        TokenState::default().try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + TokenState::LEN);
+        assert!(LegacyTokenState::SPACE <= data.len());
+    }
+
+    #[test]
+    fn account_lengths_match_their_layouts() {
+        let mut data = Vec::new();
+        OrderConfig::default().try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + OrderConfig::LEN);
+
+        let mut data = Vec::new();
+        PointsCredit::default().try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + PointsCredit::LEN);
+
+        let mut data = Vec::new();
+        Coupon {
+            item_id: Some("x".repeat(MAX_ITEM_ID_LEN)),
+            ..Coupon::default()
+        }
+        .try_serialize(&mut data)
+        .unwrap();
+        assert_eq!(data.len(), 8 + Coupon::LEN);
+    }
+}